@@ -0,0 +1,439 @@
+//! Informed solver for pusher
+//!
+//! It runs A* over pushes: a state is a position of balls and the area which the player can walk,
+//! and an edge is a walk to a ball followed by a push, by the same rule as `Stage::move_player`.
+//! In reverse mode, the edge is a walk followed by a pull.
+//! States are `bitboard::State`s, and visited states are identified by keys like `Stage::canonical_key`.
+//! The heuristic is the minimum cost matching of balls to goals by push distances,
+//! which is found by the Hungarian algorithm. It never overestimates,
+//! so the solution has the fewest pushes.
+
+use super::bitboard::{Bitset, Board, State, DEFAULT_WORDS, MAX_SQUARES};
+use super::error::Error;
+use super::solver::Statistics;
+use super::stage::{Direction, Stage};
+use super::zobrist::{self, TranspositionTable};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Cost of an impossible push or matching
+const INFINITY: u32 = u32::MAX;
+
+/// Describes limits of a search. `None` means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of expanded states
+    pub nodes: Option<usize>,
+    /// Maximum time of the search
+    pub time: Option<Duration>,
+}
+
+/// Describes the result of a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Directions of a push-optimal solution
+    Solved(Vec<Direction>),
+    /// The stage cannot be solved.
+    Unsolvable,
+    /// The search is stopped by a limit.
+    LimitReached,
+}
+
+/// Parent of a square in walks: `None` if the square cannot be reached, `Some(None)` for the player,
+/// and `Some(Some((from, direction)))` for the previous square of the shortest walk.
+type Parent = Option<Option<(usize, Direction)>>;
+
+/// Describes a searched state, which links to its parent.
+struct Node<const W: usize> {
+    /// Index of the parent node
+    parent: usize,
+    /// Walk and push from the parent node
+    moves: Vec<Direction>,
+    /// Positions after the moves
+    state: State<W>,
+}
+
+/// Finds a push-optimal solution from current state of `stage` by A* within `limits`.\
+/// In reverse mode, the solution has the fewest pulls.
+/// # Errors
+/// It returns `Err(Error::StageTooLarge)` if the stage has more than `bitboard::MAX_SQUARES` squares.
+/// # Example
+/// ```
+/// use pusher::astar::{solve, Limits, Outcome};
+/// use pusher::Stage;
+/// let stage = Stage::new("#######\n#@....#\n#.OO..#\n#..++.#\n#######").unwrap();
+/// let (outcome, statistics) = solve(&stage, Limits::default()).unwrap();
+/// match outcome {
+///     Outcome::Solved(solution) => {
+///         let mut played = stage.clone();
+///         solution.iter().for_each(|&d| played.move_player(d).unwrap());
+///         assert!(played.is_won());
+///         assert_eq!(played.pushes(), 4);
+///     }
+///     _ => panic!("not solved"),
+/// }
+/// assert!(statistics.expanded > 0);
+/// ```
+pub fn solve(stage: &Stage, limits: Limits) -> Result<(Outcome, Statistics), Error> {
+    match stage.width() * stage.height() {
+        squares if squares <= Bitset::<DEFAULT_WORDS>::CAPACITY => {
+            search::<DEFAULT_WORDS>(stage, limits)
+        }
+        squares if squares <= Bitset::<64>::CAPACITY => search::<64>(stage, limits),
+        squares if squares <= Bitset::<256>::CAPACITY => search::<256>(stage, limits),
+        squares => Err(Error::StageTooLarge {
+            squares,
+            max: MAX_SQUARES,
+        }),
+    }
+}
+
+/// Runs `solve` over bitboards of `W` words.
+fn search<const W: usize>(stage: &Stage, limits: Limits) -> Result<(Outcome, Statistics), Error> {
+    let started = Instant::now();
+    let mut statistics = Statistics::default();
+    let (board, root) = Board::<W>::from_stage(stage)?;
+    let distances: Vec<Vec<u32>> = (0..board.width() * board.height())
+        .filter(|&index| board.is_goal(index))
+        .map(|goal| push_distances(&board, goal))
+        .collect();
+    let root_estimate = estimate(&root, &distances);
+    if root_estimate == INFINITY || stage.is_deadlocked() {
+        return Ok((Outcome::Unsolvable, statistics));
+    }
+    let mut nodes = vec![Node {
+        parent: usize::MAX,
+        moves: Vec::new(),
+        state: root,
+    }];
+    let mut best = TranspositionTable::new();
+    let mut closed = HashSet::new();
+    let mut heap = BinaryHeap::new();
+    heap.push(Reverse((root_estimate, 0u32, 0usize)));
+    let outcome = loop {
+        let Reverse((_, pushes, index)) = match heap.pop() {
+            Some(item) => item,
+            None => break Outcome::Unsolvable,
+        };
+        let current = nodes[index].state;
+        let (reached, parents) = walks(&board, &current);
+        if !closed.insert(canonical_key(&board, &current, &reached)) {
+            continue;
+        }
+        if board.is_won(&current) {
+            break Outcome::Solved(trace(&nodes, index));
+        }
+        if limits.nodes.map_or(false, |n| statistics.expanded >= n)
+            || limits.time.map_or(false, |t| started.elapsed() >= t)
+        {
+            break Outcome::LimitReached;
+        }
+        statistics.expanded += 1;
+        for &pos in &reached {
+            for &direction in &Direction::ALL {
+                let mut next = State {
+                    player: pos,
+                    ..current
+                };
+                if board.step(&mut next, direction) != Ok(true) {
+                    continue;
+                }
+                // A pushed ball is ahead of the player. Reverse mode has no dead squares.
+                let ahead = board.neighbor(next.player, direction);
+                if ahead.map_or(false, |ball| board.is_dead_square(ball)) {
+                    continue;
+                }
+                statistics.generated += 1;
+                let key = balls_key(&next) ^ zobrist::player_key(next.player);
+                if !best.insert_if(key, pushes + 1, |new, old| new < old) {
+                    continue;
+                }
+                let h = estimate(&next, &distances);
+                if h == INFINITY {
+                    continue;
+                }
+                let mut moves = walk(&parents, pos);
+                moves.push(direction);
+                nodes.push(Node {
+                    parent: index,
+                    moves,
+                    state: next,
+                });
+                heap.push(Reverse((pushes + 1 + h, pushes + 1, nodes.len() - 1)));
+            }
+        }
+    };
+    statistics.elapsed = started.elapsed();
+    Ok((outcome, statistics))
+}
+
+/// Finds squares which the player of `state` can walk to without moving a ball.
+/// # Returns
+/// Reached squares from the nearest, and parents of squares
+fn walks<const W: usize>(board: &Board<W>, state: &State<W>) -> (Vec<usize>, Vec<Parent>) {
+    let mut parents = vec![None; board.width() * board.height()];
+    let mut reached = vec![state.player];
+    parents[state.player] = Some(None);
+    let mut next_index = 0;
+    while next_index < reached.len() {
+        let pos = reached[next_index];
+        next_index += 1;
+        for &direction in &Direction::ALL {
+            let mut next = State {
+                player: pos,
+                ..*state
+            };
+            if board.step(&mut next, direction) == Ok(false) && parents[next.player].is_none() {
+                parents[next.player] = Some(Some((pos, direction)));
+                reached.push(next.player);
+            }
+        }
+    }
+    (reached, parents)
+}
+
+/// Follows `parents` from `to` and returns the walk from the player.
+fn walk(parents: &[Parent], mut to: usize) -> Vec<Direction> {
+    let mut directions = Vec::new();
+    while let Some(Some((parent, direction))) = parents[to] {
+        directions.push(direction);
+        to = parent;
+    }
+    directions.reverse();
+    directions
+}
+
+/// Returns the Zobrist key of the balls of `state`.
+fn balls_key<const W: usize>(state: &State<W>) -> u64 {
+    state
+        .balls
+        .iter()
+        .fold(0, |key, ball| key ^ zobrist::ball_key(ball))
+}
+
+/// Returns the key of `state` with the top-left square of `reached`, like `Stage::canonical_key`.\
+/// In reverse mode, a walk away from a ball pulls it, so walks are not always reversible
+/// and the player keeps its own square.
+fn canonical_key<const W: usize>(board: &Board<W>, state: &State<W>, reached: &[usize]) -> u64 {
+    let player = if board.is_reverse() {
+        state.player
+    } else {
+        reached.iter().copied().min().unwrap_or(state.player)
+    };
+    balls_key(state) ^ zobrist::player_key(player)
+}
+
+/// Counts the fewest pushes of a ball from every square to `goal`, ignoring other balls.
+/// In reverse mode, it counts pulls instead.
+/// # Returns
+/// Push distances indexed by squares, `INFINITY` if the ball cannot reach the goal.
+fn push_distances<const W: usize>(board: &Board<W>, goal: usize) -> Vec<u32> {
+    let is_floor = |pos: Option<usize>| pos.map_or(false, |pos| !board.is_wall(pos));
+    let mut distances = vec![INFINITY; board.width() * board.height()];
+    let mut queue = VecDeque::new();
+    distances[goal] = 0;
+    queue.push_back(goal);
+    while let Some(pos) = queue.pop_front() {
+        for &direction in &Direction::ALL {
+            // A ball on `from` is moved to `pos` by the player on `player`.
+            let from = board.neighbor(pos, direction.opposite());
+            let player = if board.is_reverse() {
+                board.neighbor(pos, direction)
+            } else {
+                from.and_then(|from| board.neighbor(from, direction.opposite()))
+            };
+            if let (true, true, Some(from)) = (is_floor(from), is_floor(player), from) {
+                if distances[from] == INFINITY {
+                    distances[from] = distances[pos] + 1;
+                    queue.push_back(from);
+                }
+            }
+        }
+    }
+    distances
+}
+
+/// Returns the minimum total push distance of a matching from balls to goals.
+fn estimate<const W: usize>(state: &State<W>, distances: &[Vec<u32>]) -> u32 {
+    let costs: Vec<Vec<u32>> = state
+        .balls
+        .iter()
+        .map(|ball| distances.iter().map(|goal| goal[ball]).collect())
+        .collect();
+    hungarian(&costs)
+}
+
+/// Finds the minimum cost of a perfect matching of a square cost matrix (Hungarian algorithm).
+/// # Returns
+/// The minimum cost, or `INFINITY` if every perfect matching has an `INFINITY` cost.
+fn hungarian(costs: &[Vec<u32>]) -> u32 {
+    let n = costs.len();
+    // Larger than any finite total, so a matching with an impossible pair stands out.
+    let big = (n as i64 + 1) * (u32::MAX as i64 - 1);
+    let cost = |i: usize, j: usize| match costs[i - 1][j - 1] {
+        INFINITY => big,
+        c => c as i64,
+    };
+    // Potentials of rows and columns, and the row matched to each column (1-based)
+    let (mut u, mut v) = (vec![0i64; n + 1], vec![0i64; n + 1]);
+    let mut matched = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+    for i in 1..=n {
+        matched[0] = i;
+        let mut j0 = 0;
+        let mut min = vec![i64::MAX; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[j0] = true;
+            let i0 = matched[j0];
+            let (mut delta, mut j1) = (i64::MAX, 0);
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let current = cost(i0, j) - u[i0] - v[j];
+                if current < min[j] {
+                    min[j] = current;
+                    way[j] = j0;
+                }
+                if min[j] < delta {
+                    delta = min[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[matched[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if matched[j0] == 0 {
+                break;
+            }
+        }
+        while j0 != 0 {
+            let j1 = way[j0];
+            matched[j0] = matched[j1];
+            j0 = j1;
+        }
+    }
+    let total: i64 = (1..=n).map(|j| cost(matched[j], j)).sum();
+    if total >= big {
+        INFINITY
+    } else {
+        total as u32
+    }
+}
+
+/// Follows parents from `index` and returns directions from the root.
+fn trace<const W: usize>(nodes: &[Node<W>], mut index: usize) -> Vec<Direction> {
+    let mut parts = Vec::new();
+    while nodes[index].parent != usize::MAX {
+        parts.push(&nodes[index].moves);
+        index = nodes[index].parent;
+    }
+    parts.into_iter().rev().flatten().copied().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::solver::{self, SolveMode};
+    #[test]
+    fn matching() {
+        assert_eq!(hungarian(&[]), 0);
+        assert_eq!(hungarian(&[vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]]), 5);
+        assert_eq!(hungarian(&[vec![1, INFINITY], vec![2, INFINITY]]), INFINITY);
+        assert_eq!(hungarian(&[vec![INFINITY, 1], vec![2, 7]]), 3);
+    }
+    #[test]
+    fn push_optimal() {
+        let stage =
+            Stage::new("########\n#@.....#\n#.O.O..#\n#..O...#\n#.+.+.+#\n########").unwrap();
+        let (outcome, statistics) = solve(&stage, Limits::default()).unwrap();
+        let solution = match outcome {
+            Outcome::Solved(solution) => solution,
+            outcome => panic!("{:?}", outcome),
+        };
+        let mut played = stage.clone();
+        solution
+            .iter()
+            .for_each(|&d| played.move_player(d).unwrap());
+        assert!(played.is_won());
+        let uniform = solver::solve(&stage, SolveMode::Pushes).unwrap();
+        let mut expected = stage.clone();
+        uniform
+            .iter()
+            .for_each(|&d| expected.move_player(d).unwrap());
+        assert_eq!(played.pushes(), expected.pushes());
+        assert!(statistics.generated >= statistics.expanded);
+    }
+    #[test]
+    fn limits() {
+        let stage =
+            Stage::new("########\n#@.....#\n#.O.O..#\n#..O...#\n#.+.+.+#\n########").unwrap();
+        let limits = Limits {
+            nodes: Some(1),
+            time: None,
+        };
+        let (outcome, statistics) = solve(&stage, limits).unwrap();
+        assert_eq!((outcome, statistics.expanded), (Outcome::LimitReached, 1));
+        let stuck = Stage::new("#####\n#O.@#\n#..+#\n#####").unwrap();
+        assert_eq!(
+            solve(&stuck, Limits::default()).unwrap().0,
+            Outcome::Unsolvable
+        );
+        let won = Stage::new("#@#").unwrap();
+        assert_eq!(
+            solve(&won, Limits::default()).unwrap().0,
+            Outcome::Solved(Vec::new())
+        );
+    }
+    #[test]
+    fn reverse() {
+        let stage = Stage::new("#######\n#@....#\n#.OO..#\n#..++.#\n#######")
+            .unwrap()
+            .reversed();
+        let solution = match solve(&stage, Limits::default()).unwrap().0 {
+            Outcome::Solved(solution) => solution,
+            outcome => panic!("{:?}", outcome),
+        };
+        let mut played = stage.clone();
+        solution
+            .iter()
+            .for_each(|&d| played.move_player(d).unwrap());
+        assert!(played.is_won());
+        let uniform = solver::solve(&stage, SolveMode::Pushes).unwrap();
+        let mut expected = stage.clone();
+        uniform
+            .iter()
+            .for_each(|&d| expected.move_player(d).unwrap());
+        assert_eq!(played.pushes(), expected.pushes());
+    }
+    #[test]
+    fn large() {
+        let mut lines = vec!["#".repeat(60)];
+        lines.push(format!("#@O{}+#", ".".repeat(55)));
+        lines.extend((0..37).map(|_| format!("#{}#", ".".repeat(58))));
+        lines.push("#".repeat(60));
+        let stage = Stage::new(&lines.join("\n")).unwrap();
+        assert_eq!((stage.width(), stage.height()), (60, 40));
+        let solution = match solve(&stage, Limits::default()).unwrap().0 {
+            Outcome::Solved(solution) => solution,
+            outcome => panic!("{:?}", outcome),
+        };
+        assert_eq!(solution.len(), 56);
+        let huge = Stage::new(&format!("#@O+{}#", ".".repeat(MAX_SQUARES))).unwrap();
+        assert_eq!(
+            solve(&huge, Limits::default()),
+            Err(Error::StageTooLarge {
+                squares: MAX_SQUARES + 5,
+                max: MAX_SQUARES
+            })
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! Bitboard representation for pusher
+//!
+//! A `Board` keeps static parts of a stage as bitsets: walls, goals and dead squares.
+//! A `State` keeps only the player index and a bitset of balls,
+//! so it is cheap to copy, hash and compare in searches and rollouts.
+//! Bitsets have a number of words fixed at compile time, so a board holds at most 64 squares a word.
+//! Searches choose the number of words by the size of the stage, up to `MAX_SQUARES` squares.
+
+use super::error::Error;
+use super::format::StageFormat;
+use super::stage::{self, Direction, ObjectType, Stage, Tile};
+use super::zobrist;
+
+/// Bits in a word of `Bitset`
+const WORD_BITS: usize = 64;
+/// Default number of words of `Bitset`, which holds 32x32 squares
+pub const DEFAULT_WORDS: usize = 16;
+/// Maximum number of squares of a stage which searches support, which is 128x128
+pub const MAX_SQUARES: usize = WORD_BITS * 256;
+
+/// Describes a fixed size set of indices less than `Bitset::CAPACITY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bitset<const WORDS: usize = DEFAULT_WORDS> {
+    words: [u64; WORDS],
+}
+
+impl<const WORDS: usize> Bitset<WORDS> {
+    /// Number of indices which the set can hold
+    pub const CAPACITY: usize = WORD_BITS * WORDS;
+    /// Makes an empty set.
+    pub fn new() -> Bitset<WORDS> {
+        Bitset { words: [0; WORDS] }
+    }
+    /// Checks `index` is in the set.
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+    /// Adds `index` to the set.
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / WORD_BITS] |= 1 << (index % WORD_BITS);
+    }
+    /// Removes `index` from the set.
+    pub fn remove(&mut self, index: usize) {
+        self.words[index / WORD_BITS] &= !(1 << (index % WORD_BITS));
+    }
+    /// Returns the number of indices in the set.
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+    /// Checks the set has no index.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+    /// Returns the number of indices in both sets.
+    pub fn intersection_len(&self, other: &Bitset<WORDS>) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+    /// Checks every index of the set is in `other`.
+    pub fn is_subset(&self, other: &Bitset<WORDS>) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .all(|(a, b)| a & !b == 0)
+    }
+    /// Iterates indices in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                if rest == 0 {
+                    return None;
+                }
+                let bit = rest.trailing_zeros() as usize;
+                rest &= rest - 1;
+                Some(i * WORD_BITS + bit)
+            })
+        })
+    }
+}
+
+impl<const WORDS: usize> Default for Bitset<WORDS> {
+    fn default() -> Bitset<WORDS> {
+        Bitset::new()
+    }
+}
+
+/// Describes positions of the player and balls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct State<const WORDS: usize = DEFAULT_WORDS> {
+    /// Index of the player
+    pub player: usize,
+    /// Indices of balls
+    pub balls: Bitset<WORDS>,
+}
+
+impl<const WORDS: usize> State<WORDS> {
+    /// Returns Zobrist key of the state, which is the same as `Stage::zobrist_key` of the stage.
+    pub fn zobrist_key(&self) -> u64 {
+        self.balls
+            .iter()
+            .fold(zobrist::player_key(self.player), |key, ball| {
+                key ^ zobrist::ball_key(ball)
+            })
+    }
+}
+
+/// Describes static parts of a stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board<const WORDS: usize = DEFAULT_WORDS> {
+    width: usize,
+    height: usize,
+    /// Walls and outside, where nothing can enter
+    walls: Bitset<WORDS>,
+    /// Outside, which is a subset of walls
+    outside: Bitset<WORDS>,
+    goals: Bitset<WORDS>,
+    /// Squares from which a ball cannot reach any goal
+    dead: Bitset<WORDS>,
+    /// Whether the player pulls balls instead of pushing
+    reverse: bool,
+}
+
+impl<const WORDS: usize> Board<WORDS> {
+    /// Splits current state of `stage` into the board and the state.
+    /// The board keeps reverse mode of `stage`.
+    /// # Errors
+    /// It returns `Err(Error::StageTooLarge)` if the stage has more than `Bitset::CAPACITY` squares.
+    /// # Example
+    /// ```
+    /// use pusher::bitboard::Board;
+    /// use pusher::{Direction, Stage};
+    /// let stage = Stage::new("#@.O+#").unwrap();
+    /// let (board, mut state): (Board, _) = Board::from_stage(&stage).unwrap();
+    /// let copy = state;
+    /// board.step(&mut state, Direction::Right).unwrap();
+    /// assert_eq!(board.step(&mut state, Direction::Right), Ok(true));
+    /// assert!(board.is_won(&state));
+    /// assert_ne!(state, copy);
+    /// assert!(board.to_stage(&state).unwrap().is_won());
+    /// ```
+    pub fn from_stage(stage: &Stage) -> Result<(Board<WORDS>, State<WORDS>), Error> {
+        let squares = stage.width() * stage.height();
+        if squares > Bitset::<WORDS>::CAPACITY {
+            return Err(Error::StageTooLarge {
+                squares,
+                max: Bitset::<WORDS>::CAPACITY,
+            });
+        }
+        let mut board = Board {
+            width: stage.width(),
+            height: stage.height(),
+            walls: Bitset::new(),
+            outside: Bitset::new(),
+            goals: Bitset::new(),
+            dead: Bitset::new(),
+            reverse: stage.is_reverse(),
+        };
+        let mut state = State {
+            player: 0,
+            balls: Bitset::new(),
+        };
+        for (index, (pos, tile)) in stage.tiles().enumerate() {
+            match tile {
+                Tile::Wall => board.walls.insert(index),
+                Tile::Outside => {
+                    board.walls.insert(index);
+                    board.outside.insert(index);
+                }
+                Tile::Plain(_) | Tile::Goal(_) => (),
+            }
+            if tile.is_goal() {
+                board.goals.insert(index);
+            }
+            match tile.object() {
+                Some(ObjectType::Ball) => state.balls.insert(index),
+                Some(ObjectType::Player) => state.player = index,
+                _ => (),
+            }
+            if stage.is_dead_square(pos) {
+                board.dead.insert(index);
+            }
+        }
+        Ok((board, state))
+    }
+    /// Makes a stage of the board and `state` in the mode of the board. History of the moves is not restored.
+    /// # Errors
+    /// It returns `Err` propagated from `Stage::with_format`. (e.g. Balls are not as many as goals)
+    pub fn to_stage(&self, state: &State<WORDS>) -> Result<Stage, Error> {
+        let mut string = String::with_capacity((self.width + 1) * self.height);
+        for index in 0..self.width * self.height {
+            let tile = if self.walls.contains(index) {
+                if self.outside.contains(index) {
+                    Tile::Outside
+                } else {
+                    Tile::Wall
+                }
+            } else {
+                let object = if index == state.player {
+                    ObjectType::Player
+                } else if state.balls.contains(index) {
+                    ObjectType::Ball
+                } else {
+                    ObjectType::Empty
+                };
+                if self.goals.contains(index) {
+                    Tile::Goal(object)
+                } else {
+                    Tile::Plain(object)
+                }
+            };
+            string.push(StageFormat::Xsb.tile_char(&tile).unwrap_or(' '));
+            if index % self.width == self.width - 1 {
+                string.push('\n');
+            }
+        }
+        let stage = Stage::with_format(&string, StageFormat::Xsb)?;
+        Ok(if self.reverse {
+            stage.into_reverse()
+        } else {
+            stage
+        })
+    }
+    /// Getter of the width
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// Getter of the height
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Checks the player pulls balls instead of pushing.
+    pub fn is_reverse(&self) -> bool {
+        self.reverse
+    }
+    /// Checks `index` is a wall or outside.
+    pub fn is_wall(&self, index: usize) -> bool {
+        self.walls.contains(index)
+    }
+    /// Checks `index` is a goal.
+    pub fn is_goal(&self, index: usize) -> bool {
+        self.goals.contains(index)
+    }
+    /// Checks a ball on `index` can never reach any goal.
+    pub fn is_dead_square(&self, index: usize) -> bool {
+        self.dead.contains(index)
+    }
+    /// Checks every ball of `state` is on a goal.
+    pub fn is_won(&self, state: &State<WORDS>) -> bool {
+        state.balls.is_subset(&self.goals)
+    }
+    /// Returns the number of balls on goals.
+    pub fn matched_goals(&self, state: &State<WORDS>) -> usize {
+        state.balls.intersection_len(&self.goals)
+    }
+    /// Returns index next to `index` in `direction`, or `None` if it is out of the board.
+    pub fn neighbor(&self, index: usize, direction: Direction) -> Option<usize> {
+        stage::neighbor(index, direction, self.width, self.width * self.height)
+    }
+    /// Moves the player of `state` and pushes a ball by the same rule as `Stage::move_player`.
+    /// In reverse mode, the player pulls a ball behind instead.
+    /// # Returns
+    /// `true` if a ball is pushed or pulled.
+    /// # Errors
+    /// It returns `Err(Error)` when the player is blocked by a wall, a ball or boundary,
+    /// and `state` is not changed.
+    pub fn step(&self, state: &mut State<WORDS>, direction: Direction) -> Result<bool, Error> {
+        let next = self
+            .neighbor(state.player, direction)
+            .ok_or(Error::BlockedByBoundary)?;
+        if self.outside.contains(next) {
+            return Err(Error::BlockedByBoundary);
+        }
+        if self.walls.contains(next) {
+            return Err(Error::BlockedByWall);
+        }
+        if self.reverse {
+            if state.balls.contains(next) {
+                return Err(Error::BlockedByBall);
+            }
+            let behind = self
+                .neighbor(state.player, direction.opposite())
+                .filter(|&behind| state.balls.contains(behind));
+            if let Some(behind) = behind {
+                state.balls.remove(behind);
+                state.balls.insert(state.player);
+            }
+            state.player = next;
+            return Ok(behind.is_some());
+        }
+        if !state.balls.contains(next) {
+            state.player = next;
+            return Ok(false);
+        }
+        let beyond = self.neighbor(next, direction).ok_or(Error::BlockedByBall)?;
+        if self.walls.contains(beyond) || state.balls.contains(beyond) {
+            return Err(Error::BlockedByBall);
+        }
+        state.balls.remove(next);
+        state.balls.insert(beyond);
+        state.player = next;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+    #[test]
+    fn bitset() {
+        let mut set: Bitset = Bitset::new();
+        assert!(set.is_empty());
+        set.insert(3);
+        set.insert(64);
+        set.insert(129);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 64, 129]);
+        assert_eq!(set.len(), 3);
+        set.remove(64);
+        assert!(!set.contains(64));
+        let mut other = set;
+        other.insert(100);
+        assert!(set.is_subset(&other) && !other.is_subset(&set));
+        assert_eq!(set.intersection_len(&other), 2);
+    }
+    #[test]
+    fn same_as_stage() {
+        let data = "#######\n###...#\n#@O.O.#\n#..+.+#\n#######\n";
+        let mut stage = Stage::new(data).unwrap();
+        let (board, mut state): (Board, _) = Board::from_stage(&stage).unwrap();
+        assert!(board.is_dead_square(1 + 2 * 7));
+        let mut seen = HashSet::new();
+        seen.insert(state);
+        for &direction in &[
+            Direction::Right,
+            Direction::Up,
+            Direction::Down,
+            Direction::Right,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ] {
+            assert_eq!(
+                board.step(&mut state, direction),
+                stage.step(direction),
+                "{:?}",
+                direction
+            );
+            seen.insert(state);
+            assert_eq!(board.matched_goals(&state), stage.matched_goals() as usize);
+            assert_eq!(state.zobrist_key(), stage.zobrist_key());
+        }
+        assert_eq!(
+            board
+                .to_stage(&state)
+                .unwrap()
+                .to_format_string(StageFormat::Xsb),
+            stage.to_format_string(StageFormat::Xsb)
+        );
+        assert_eq!(Board::from_stage(&stage), Ok((board, state)));
+        assert!(seen.len() > 1);
+    }
+    #[test]
+    fn reverse() {
+        let mut stage = Stage::new("#######\n#@.O.+#\n#######").unwrap().reversed();
+        let (board, mut state): (Board, _) = Board::from_stage(&stage).unwrap();
+        assert!(board.is_reverse());
+        for &direction in &[
+            Direction::Right,
+            Direction::Right,
+            Direction::Right,
+            Direction::Left,
+            Direction::Left,
+            Direction::Right,
+            Direction::Up,
+        ] {
+            assert_eq!(
+                board.step(&mut state, direction),
+                stage.step(direction),
+                "{:?}",
+                direction
+            );
+        }
+        assert!(board.is_won(&state) && stage.is_won());
+        let rebuilt = board.to_stage(&state).unwrap();
+        assert!(rebuilt.is_reverse());
+        assert_eq!(
+            rebuilt.to_format_string(StageFormat::Xsb),
+            stage.to_format_string(StageFormat::Xsb)
+        );
+        let large = Stage::new(&format!("#@O+{}#", ".".repeat(2000))).unwrap();
+        assert_eq!(
+            Board::<DEFAULT_WORDS>::from_stage(&large),
+            Err(Error::StageTooLarge {
+                squares: 2005,
+                max: 1024
+            })
+        );
+        let (board, mut state) = Board::<64>::from_stage(&large).unwrap();
+        assert_eq!(board.step(&mut state, Direction::Right), Ok(true));
+        assert!(board.is_won(&state));
+    }
+}
@@ -0,0 +1,214 @@
+//! Collection of stages
+//!
+//! A collection file contains levels separated by blank lines.
+//! `Title:` and `Author:` lines describe the level in the same block,
+//! and other lines which are not a part of a stage are ignored as comments.
+
+use super::error::Error;
+use super::format::StageFormat;
+use super::stage::Stage;
+
+/// Characters which can appear in a stage line of any format.
+const STAGE_CHRS: &str = "#.O+@$* -_";
+
+/// Describes a level in a collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Level {
+    /// Title of the level
+    pub title: Option<String>,
+    /// Author of the level
+    pub author: Option<String>,
+    /// Stage data of the level
+    pub data: String,
+    /// Line number of the first stage line in the file (1-based)
+    pub line: usize,
+}
+
+impl Level {
+    /// Creates a stage of the level.\
+    /// The format is detected if `format` is `None`.
+    /// # Errors
+    /// It returns `Err` propagated from `Stage::with_format`.
+    pub fn stage(&self, format: Option<StageFormat>) -> Result<Stage, Error> {
+        let format = format.unwrap_or_else(|| StageFormat::detect(&self.data));
+        Stage::with_format(&self.data, format)
+    }
+    /// Fills title and author from `other` if they are not set yet.
+    fn merge(&mut self, other: Level) {
+        if self.title.is_none() {
+            self.title = other.title;
+        }
+        if self.author.is_none() {
+            self.author = other.author;
+        }
+    }
+}
+
+/// Describes a collection of levels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collection {
+    levels: Vec<Level>,
+}
+
+impl Collection {
+    /// Parses a collection file.\
+    /// A block without stage lines gives its title and author to the previous level,
+    /// or the next level if it is the first block.
+    /// # Example
+    /// ```
+    /// use pusher::collection::Collection;
+    /// let collection = Collection::parse(
+    ///     "; My levels\n\
+    ///     #@O+#\n\
+    ///     Title: First\n\
+    ///     \n\
+    ///     #+O@#\n\
+    ///     Title: Second\n\
+    ///     Author: km19809\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(collection.len(), 2);
+    /// assert_eq!(collection.levels()[1].data, "#+O@#\n");
+    /// assert_eq!(collection.levels()[1].author.as_deref(), Some("km19809"));
+    /// ```
+    /// # Errors
+    /// It returns `Err(Error::NoLevel)` when no level is found.
+    pub fn parse(string: &str) -> Result<Collection, Error> {
+        let mut levels: Vec<Level> = Vec::new();
+        let mut pending: Option<Level> = None;
+        let mut block = Level {
+            title: None,
+            author: None,
+            data: String::new(),
+            line: 0,
+        };
+        for (index, line) in string.lines().chain(std::iter::once("")).enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() {
+                let finished = std::mem::replace(
+                    &mut block,
+                    Level {
+                        title: None,
+                        author: None,
+                        data: String::new(),
+                        line: 0,
+                    },
+                );
+                if !finished.data.is_empty() {
+                    let mut level = finished;
+                    if let Some(meta) = pending.take() {
+                        level.merge(meta);
+                    }
+                    levels.push(level);
+                } else if finished.title.is_some() || finished.author.is_some() {
+                    match levels.last_mut() {
+                        Some(last) => last.merge(finished),
+                        None => pending = Some(finished),
+                    }
+                }
+            } else if let Some(title) = strip_key(line, "title") {
+                block.title = Some(title);
+            } else if let Some(author) = strip_key(line, "author") {
+                block.author = Some(author);
+            } else if is_stage_line(line) || (!block.data.is_empty() && continues_stage(line)) {
+                if block.data.is_empty() {
+                    block.line = index + 1;
+                }
+                block.data.push_str(line);
+                block.data.push('\n');
+            }
+        }
+        if levels.is_empty() {
+            Err(Error::NoLevel)
+        } else {
+            Ok(Collection { levels })
+        }
+    }
+    /// Returns levels of the collection.
+    pub fn levels(&self) -> &[Level] {
+        &self.levels
+    }
+    /// Returns the number of levels.
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+    /// Returns `true` if the collection has no level.
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+}
+
+/// Returns value of `key: value` line, whose key is case insensitive.
+fn strip_key(line: &str, key: &str) -> Option<String> {
+    let (k, v) = line.split_at(line.find(':')?);
+    if k.trim().eq_ignore_ascii_case(key) {
+        Some(String::from(v[1..].trim()))
+    } else {
+        None
+    }
+}
+
+/// Checks the line can start a stage.\
+/// A stage line has a wall and consists of stage characters only.
+fn is_stage_line(line: &str) -> bool {
+    line.contains('#') && has_only_stage_chrs(line)
+}
+
+/// Checks the line is a part of the stage which has started.\
+/// Open rows without a wall are a part of the stage,
+/// but a line with other characters is not, even if it starts with a wall like `# Level 2`.
+fn continues_stage(line: &str) -> bool {
+    has_only_stage_chrs(line)
+}
+
+/// Checks the line consists of stage characters only.
+fn has_only_stage_chrs(line: &str) -> bool {
+    line.chars().all(|c| STAGE_CHRS.contains(c))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn single_level() {
+        let c = Collection::parse("#####\n#@O+#\n#####").unwrap();
+        assert_eq!(c.len(), 1);
+        assert_eq!(c.levels()[0].data, "#####\n#@O+#\n#####\n");
+        assert_eq!(c.levels()[0].title, None);
+    }
+    #[test]
+    fn metadata_blocks() {
+        let c = Collection::parse(
+            "Title: One\n\n#@O+#\n\nTitle: ignored\n\n\n#+O@#\n\nTITLE: Two\nauthor : me\n",
+        )
+        .unwrap();
+        assert_eq!(c.len(), 2);
+        assert_eq!(c.levels()[0].title.as_deref(), Some("One"));
+        assert_eq!(c.levels()[1].title.as_deref(), Some("Two"));
+        assert_eq!(c.levels()[1].author.as_deref(), Some("me"));
+        assert_eq!((c.levels()[0].line, c.levels()[1].line), (3, 8));
+    }
+    #[test]
+    fn typo_in_stage() {
+        let c = Collection::parse("; comment\n#####\n#@Ox#\n#####\n").unwrap();
+        assert_eq!(c.levels()[0].data, "#####\n#####\n");
+        assert!(c.levels()[0].stage(None).is_err());
+    }
+    #[test]
+    fn comment_after_stage() {
+        let c = Collection::parse("#####\n#@O+#\n#####\n# Level 2\n\n#+O@#\n").unwrap();
+        assert_eq!(c.len(), 2);
+        assert_eq!(c.levels()[0].data, "#####\n#@O+#\n#####\n");
+        assert!(c.levels()[0].stage(None).is_ok());
+    }
+    #[test]
+    fn open_row() {
+        let c = Collection::parse("; open\n#####\n#@O.#\n..+..\n#####\n; end\n").unwrap();
+        assert_eq!(c.levels()[0].data, "#####\n#@O.#\n..+..\n#####\n");
+        assert!(c.levels()[0].stage(None).is_ok());
+    }
+    #[test]
+    fn empty() {
+        assert_eq!(Collection::parse("; nothing\n\n"), Err(Error::NoLevel));
+    }
+}
@@ -0,0 +1,161 @@
+//! Deadlock detection for pusher
+//!
+//! A deadlock is a state which can never be solved.
+//! * Dead square: A ball on it cannot reach any goal even if there are no other balls.
+//! * Freeze: A ball cannot move anymore and it is not on a goal.
+
+use super::stage::{neighbor, Direction, ObjectType, Tile};
+
+/// Describes an axis which a ball moves along.
+#[derive(Clone, Copy)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// Checks the tile at `pos` is a wall. Outside and out of the stage are also walls.
+fn is_wall(data: &[Tile], pos: Option<usize>) -> bool {
+    match pos {
+        Some(pos) => matches!(data[pos], Tile::Wall | Tile::Outside),
+        None => true,
+    }
+}
+
+/// Checks the tile at `pos` has a ball.
+fn has_ball(data: &[Tile], pos: usize) -> bool {
+    matches!(
+        data[pos],
+        Tile::Plain(ObjectType::Ball) | Tile::Goal(ObjectType::Ball)
+    )
+}
+
+/// Finds dead squares, from which a ball can never be pushed to any goal.\
+/// It pulls a ball from every goal, and squares which are never reached are dead.
+/// Walls and outside are not dead squares.
+pub(crate) fn dead_squares(data: &[Tile], width: usize) -> Vec<bool> {
+    let len = data.len();
+    let mut live = vec![false; len];
+    let mut queue: Vec<usize> = (0..len)
+        .filter(|&i| matches!(data[i], Tile::Goal(_)))
+        .collect();
+    for &goal in &queue {
+        live[goal] = true;
+    }
+    while let Some(pos) = queue.pop() {
+        for &direction in &Direction::ALL {
+            let to = neighbor(pos, direction, width, len);
+            let player = to.and_then(|to| neighbor(to, direction, width, len));
+            if let (Some(to), false, false) = (to, is_wall(data, to), is_wall(data, player)) {
+                if !live[to] {
+                    live[to] = true;
+                    queue.push(to);
+                }
+            }
+        }
+    }
+    (0..len)
+        .map(|i| !live[i] && !matches!(data[i], Tile::Wall | Tile::Outside))
+        .collect()
+}
+
+/// Checks the ball at `pos` is frozen, and a frozen ball is not on a goal.
+pub(crate) fn is_freeze_deadlock(data: &[Tile], width: usize, dead: &[bool], pos: usize) -> bool {
+    let mut checked = vec![false; data.len()];
+    let mut frozen = Vec::new();
+    is_frozen(data, width, dead, pos, &mut checked, &mut frozen)
+        && frozen
+            .iter()
+            .any(|&ball| matches!(data[ball], Tile::Plain(_)))
+}
+
+/// Checks the ball at `pos` cannot move along both axes.\
+/// Balls in `checked` are regarded as walls to avoid circular checking.
+/// Balls which are proved to be frozen are collected in `frozen`.
+fn is_frozen(
+    data: &[Tile],
+    width: usize,
+    dead: &[bool],
+    pos: usize,
+    checked: &mut Vec<bool>,
+    frozen: &mut Vec<usize>,
+) -> bool {
+    checked[pos] = true;
+    let result = is_blocked(data, width, dead, pos, Axis::Horizontal, checked, frozen)
+        && is_blocked(data, width, dead, pos, Axis::Vertical, checked, frozen);
+    if result {
+        frozen.push(pos);
+    }
+    result
+}
+
+/// Checks the ball at `pos` cannot move along `axis`.
+fn is_blocked(
+    data: &[Tile],
+    width: usize,
+    dead: &[bool],
+    pos: usize,
+    axis: Axis,
+    checked: &mut Vec<bool>,
+    frozen: &mut Vec<usize>,
+) -> bool {
+    let len = data.len();
+    let (a, b) = match axis {
+        Axis::Horizontal => (
+            neighbor(pos, Direction::Left, width, len),
+            neighbor(pos, Direction::Right, width, len),
+        ),
+        Axis::Vertical => (
+            neighbor(pos, Direction::Up, width, len),
+            neighbor(pos, Direction::Down, width, len),
+        ),
+    };
+    if is_wall(data, a) || is_wall(data, b) {
+        return true;
+    }
+    let (a, b) = (a.unwrap(), b.unwrap());
+    if dead[a] && dead[b] {
+        return true;
+    }
+    for &side in &[a, b] {
+        if has_ball(data, side)
+            && (checked[side] || is_frozen(data, width, dead, side, checked, frozen))
+        {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::format::StageFormat;
+    fn parse(s: &str) -> (Vec<Tile>, usize) {
+        let width = s.lines().next().unwrap().len();
+        let data = s
+            .lines()
+            .flat_map(str::chars)
+            .map(|c| StageFormat::Pusher.parse_char(c).unwrap())
+            .collect();
+        (data, width)
+    }
+    #[test]
+    fn corners_are_dead() {
+        let (data, width) = parse("#####\n#@..#\n#.O.#\n#..+#\n#####");
+        let dead = dead_squares(&data, width);
+        assert!(dead[6]);
+        assert!(dead[8]);
+        assert!(!dead[12]);
+        assert!(!dead[18]);
+        assert!(!dead[0]);
+    }
+    #[test]
+    fn freeze() {
+        let (data, width) = parse("######\n#@...#\n#.OO.#\n#..++#\n######");
+        let dead = dead_squares(&data, width);
+        assert!(!is_freeze_deadlock(&data, width, &dead, 14));
+        let (data, width) = parse("######\n#.OO@#\n#.++.#\n######");
+        let dead = dead_squares(&data, width);
+        assert!(is_freeze_deadlock(&data, width, &dead, 8));
+    }
+}
@@ -0,0 +1,237 @@
+//! Headless environment for reinforcement learning
+//!
+//! It wraps a `Stage` in the `reset` and `step` interface of gym.
+//! An observation is a grid of tile codes, and rewards are shaped by `Rewards`.
+
+use super::error::Error;
+use super::stage::{Direction, ObjectType, Stage, Tile};
+
+/// Actions of the environment, which are indexed by action numbers.
+pub const ACTIONS: [Direction; 4] = Direction::ALL;
+
+/// Code of outside in an observation
+pub const OUTSIDE_CODE: u8 = 0;
+/// Code of a wall in an observation
+pub const WALL_CODE: u8 = 1;
+/// Code of an empty plain in an observation
+pub const FLOOR_CODE: u8 = 2;
+/// Code of an empty goal in an observation
+pub const GOAL_CODE: u8 = 3;
+/// Code of a ball on a plain in an observation
+pub const BALL_CODE: u8 = 4;
+/// Code of a ball on a goal in an observation
+pub const BALL_ON_GOAL_CODE: u8 = 5;
+/// Code of the player on a plain in an observation
+pub const PLAYER_CODE: u8 = 6;
+/// Code of the player on a goal in an observation
+pub const PLAYER_ON_GOAL_CODE: u8 = 7;
+
+/// Describes rewards of each event of a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rewards {
+    /// Reward of every step, usually a small cost
+    pub step: f64,
+    /// Reward of a move which is blocked
+    pub invalid: f64,
+    /// Reward of pushing a ball onto a goal
+    pub ball_on_goal: f64,
+    /// Reward of pushing a ball off a goal
+    pub ball_off_goal: f64,
+    /// Reward of making a deadlock, which ends the episode
+    pub deadlock: f64,
+    /// Reward of solving the stage, which ends the episode
+    pub win: f64,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards {
+            step: -0.1,
+            invalid: -0.1,
+            ball_on_goal: 1.0,
+            ball_off_goal: -1.0,
+            deadlock: -10.0,
+            win: 10.0,
+        }
+    }
+}
+
+/// Describes the stage as a grid of tile codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation {
+    /// Width of the grid
+    pub width: usize,
+    /// Height of the grid
+    pub height: usize,
+    /// Tile codes row by row
+    pub cells: Vec<u8>,
+}
+
+impl Observation {
+    /// Encodes tiles of `stage`.
+    pub fn new(stage: &Stage) -> Observation {
+        Observation {
+            width: stage.width(),
+            height: stage.height(),
+            cells: stage.tiles().map(|(_, tile)| encode(tile)).collect(),
+        }
+    }
+    /// Returns the code at (`x`, `y`), or `None` if it is out of the grid.
+    pub fn get(&self, x: usize, y: usize) -> Option<u8> {
+        if x < self.width && y < self.height {
+            Some(self.cells[x + y * self.width])
+        } else {
+            None
+        }
+    }
+}
+
+/// Describes details of a step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Info {
+    /// Steps from the last reset, including blocked moves
+    pub steps: usize,
+    /// Whether a ball is pushed by the step
+    pub pushed: bool,
+    /// Error of the move if it is blocked
+    pub error: Option<Error>,
+    /// Count of balls on goals
+    pub matched_goals: u32,
+    /// Whether the stage cannot be solved anymore
+    pub deadlocked: bool,
+    /// Whether the stage is solved
+    pub won: bool,
+}
+
+/// Describes an environment of a stage.
+#[derive(Debug, Clone)]
+pub struct Env {
+    /// Stage at the beginning of an episode
+    initial: Stage,
+    /// Stage of the current episode
+    stage: Stage,
+    /// Rewards of events
+    rewards: Rewards,
+    /// Steps from the last reset
+    steps: usize,
+}
+
+impl Env {
+    /// Makes an environment which starts every episode from current state of `stage`.
+    pub fn new(stage: &Stage, rewards: Rewards) -> Env {
+        Env {
+            initial: stage.snapshot(),
+            stage: stage.snapshot(),
+            rewards,
+            steps: 0,
+        }
+    }
+    /// Starts a new episode.
+    /// # Returns
+    /// The first observation
+    pub fn reset(&mut self) -> Observation {
+        self.stage = self.initial.snapshot();
+        self.steps = 0;
+        Observation::new(&self.stage)
+    }
+    /// Moves the player to `direction`. A blocked move keeps the stage and gets `Rewards::invalid`.
+    /// # Returns
+    /// Observation, reward, whether the episode is done, and details of the step.
+    /// The episode is done when the stage is won or deadlocked.
+    /// # Example
+    /// ```
+    /// use pusher::env::{Env, Rewards};
+    /// use pusher::{Direction, Stage};
+    /// let mut env = Env::new(&Stage::new("#@.O+#").unwrap(), Rewards::default());
+    /// env.reset();
+    /// let (_, reward, done, _) = env.step(Direction::Right);
+    /// assert_eq!((reward, done), (-0.1, false));
+    /// let (observation, reward, done, info) = env.step(Direction::Right);
+    /// assert_eq!((reward, done, info.won), (-0.1 + 1.0 + 10.0, true, true));
+    /// assert_eq!(observation.cells, vec![1, 2, 2, 6, 5, 1]);
+    /// ```
+    pub fn step(&mut self, direction: Direction) -> (Observation, f64, bool, Info) {
+        let matched = self.stage.matched_goals();
+        let was_deadlocked = self.stage.is_deadlocked();
+        self.steps += 1;
+        let (mut reward, pushed, error) = match self.stage.step(direction) {
+            Ok(pushed) => (self.rewards.step, pushed, None),
+            Err(err) => (self.rewards.invalid, false, Some(err)),
+        };
+        let now_matched = self.stage.matched_goals();
+        if now_matched > matched {
+            reward += self.rewards.ball_on_goal * (now_matched - matched) as f64;
+        } else if now_matched < matched {
+            reward += self.rewards.ball_off_goal * (matched - now_matched) as f64;
+        }
+        let won = self.stage.is_won();
+        let deadlocked = !won && self.stage.is_deadlocked();
+        if won {
+            reward += self.rewards.win;
+        } else if deadlocked && !was_deadlocked {
+            reward += self.rewards.deadlock;
+        }
+        let info = Info {
+            steps: self.steps,
+            pushed,
+            error,
+            matched_goals: now_matched,
+            deadlocked,
+            won,
+        };
+        (
+            Observation::new(&self.stage),
+            reward,
+            won || deadlocked,
+            info,
+        )
+    }
+    /// Getter of the stage of the current episode
+    pub fn stage(&self) -> &Stage {
+        &self.stage
+    }
+}
+
+/// Converts a tile to the code of observations.
+pub fn encode(tile: &Tile) -> u8 {
+    match tile {
+        Tile::Outside => OUTSIDE_CODE,
+        Tile::Wall => WALL_CODE,
+        Tile::Plain(ObjectType::Empty) => FLOOR_CODE,
+        Tile::Goal(ObjectType::Empty) => GOAL_CODE,
+        Tile::Plain(ObjectType::Ball) => BALL_CODE,
+        Tile::Goal(ObjectType::Ball) => BALL_ON_GOAL_CODE,
+        Tile::Plain(ObjectType::Player) => PLAYER_CODE,
+        Tile::Goal(ObjectType::Player) => PLAYER_ON_GOAL_CODE,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn episode() {
+        let stage = Stage::new("#####\n#@..#\n#.O.#\n#..+#\n#####").unwrap();
+        let rewards = Rewards {
+            step: -1.0,
+            ..Rewards::default()
+        };
+        let mut env = Env::new(&stage, rewards);
+        let first = env.reset();
+        assert_eq!(first.get(1, 1), Some(PLAYER_CODE));
+        assert_eq!(first.get(5, 0), None);
+        let (observation, reward, done, info) = env.step(Direction::Up);
+        assert_eq!(observation, first);
+        assert_eq!((reward, done), (rewards.invalid, false));
+        assert_eq!(info.error, Some(Error::BlockedByWall));
+        env.step(Direction::Right);
+        env.step(Direction::Right);
+        env.step(Direction::Down);
+        let (_, reward, done, info) = env.step(Direction::Left);
+        assert_eq!((reward, done), (-1.0 + rewards.deadlock, true));
+        assert!(info.pushed && info.deadlocked);
+        assert_eq!(info.steps, 5);
+        assert_eq!(env.reset(), first);
+        assert_eq!(env.stage().moves(), 0);
+    }
+}
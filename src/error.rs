@@ -0,0 +1,177 @@
+//! Error type for pusher
+//!
+//! Every fallible function of pusher returns `Error`.
+
+use super::bitboard::MAX_SQUARES;
+use super::format::StageFormat;
+use super::vector2::Vector2;
+use std::error;
+use std::fmt;
+
+/// Describes failures of pusher.\
+/// Lines and columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Stage has a character which is not a tile of the format.
+    InvalidChar { line: usize, col: usize, ch: char },
+    /// Width or height of the stage is 0.
+    EmptyStage,
+    /// Balls are not as many as goals.
+    BallGoalMismatch { balls: u32, goals: u32 },
+    /// Stage has no player or more than one player.
+    InvalidPlayerCount { count: u32 },
+    /// Player or ball cannot move into a wall.
+    BlockedByWall,
+    /// Player cannot push a ball which is blocked.
+    BlockedByBall,
+    /// Player cannot move out of the stage.
+    BlockedByBoundary,
+    /// Object cannot move to the same place.
+    SamePlace,
+    /// Wall cannot be moved.
+    WallNotMovable,
+    /// Destination of an object is not empty.
+    DestinationOccupied,
+    /// Position is out of the stage.
+    OutOfRange {
+        pos: Vector2,
+        width: usize,
+        height: usize,
+    },
+    /// Player cannot walk to the destination, or cannot push the ball there.
+    Unreachable,
+    /// There is no move to undo.
+    NothingToUndo,
+    /// There is no move to redo.
+    NothingToRedo,
+    /// Name of the stage format is unknown.
+    UnknownFormat(String),
+    /// The format cannot represent the tile.
+    Unrepresentable {
+        format: StageFormat,
+        line: usize,
+        col: usize,
+    },
+    /// Collection has no level.
+    NoLevel,
+    /// Level number is not in the collection.
+    LevelOutOfRange { level: usize, count: usize },
+    /// Solution has a character which is not LURD. `index` is 0-based.
+    InvalidLurd { index: usize, ch: char },
+    /// A push of the solution is not a push in the stage, or vice versa. `index` is 0-based.
+    ReplayMismatch { index: usize },
+    /// Stage is not solved after the solution.
+    NotSolved,
+    /// Saved game is broken or does not match the stage.
+    InvalidSave,
+    /// Theme has a line which is not `key: glyph [color]`.
+    InvalidTheme { line: usize },
+    /// Glyphs of the theme are not the same width.
+    GlyphWidthMismatch,
+    /// Generator options are too small.
+    InvalidGeneratorOptions,
+    /// Generator cannot find a solvable stage.
+    GenerationFailed,
+    /// Search is stopped by its limits.
+    LimitReached,
+    /// Stage has more squares than a bitboard can hold.
+    StageTooLarge { squares: usize, max: usize },
+    /// Input is not a command.
+    InvalidInput,
+    /// Input is empty.
+    EmptyInput,
+    /// Input is not a level number.
+    InvalidLevelNumber,
+    /// Input is canceled.
+    Canceled,
+    /// Terminal operation has failed.
+    Terminal(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidChar { line, col, ch } => write!(
+                f,
+                "Invalid Stage: invalid charactor {:?} at line {}, column {}.",
+                ch, line, col
+            ),
+            Error::EmptyStage => write!(f, "Invalid Stage: Width or height is smaller than 1."),
+            Error::BallGoalMismatch { balls, goals } => write!(
+                f,
+                "Invalid Stage: Balls({}) are not as many as goals({}).",
+                balls, goals
+            ),
+            Error::InvalidPlayerCount { count } => write!(
+                f,
+                "Invalid Stage: Player has to be only one, but found {}.",
+                count
+            ),
+            Error::BlockedByWall => write!(f, "Blocked by the Wall."),
+            Error::BlockedByBall => write!(f, "[Player] Blocked by the Ball."),
+            Error::BlockedByBoundary => write!(f, "[Player] Blocked by boundary."),
+            Error::SamePlace => write!(f, "[Object] Trying to move to same place."),
+            Error::WallNotMovable => write!(f, "[Object] Cannot move the Wall."),
+            Error::DestinationOccupied => write!(f, "[Object] Destination is not empty."),
+            Error::OutOfRange { pos, width, height } => write!(
+                f,
+                "Index out of range! got Vector 2 {} while w:h={}:{}.",
+                pos, width, height
+            ),
+            Error::Unreachable => write!(f, "[Player] Cannot reach the destination."),
+            Error::NothingToUndo => write!(f, "[History] Nothing to undo."),
+            Error::NothingToRedo => write!(f, "[History] Nothing to redo."),
+            Error::UnknownFormat(name) => write!(f, "Unknown stage format: {}.", name),
+            Error::Unrepresentable { format, line, col } => write!(
+                f,
+                "[Format] The tile at line {}, column {} cannot be written in {} format.",
+                line, col, format
+            ),
+            Error::NoLevel => write!(f, "Invalid Collection: No level is found."),
+            Error::LevelOutOfRange { level, count } => write!(
+                f,
+                "Level {} is not found. There are {} level(s).",
+                level, count
+            ),
+            Error::InvalidLurd { index, ch } => write!(
+                f,
+                "[LURD] Invalid charactor {:?} at move {}.",
+                ch,
+                index + 1
+            ),
+            Error::ReplayMismatch { index } => write!(
+                f,
+                "[LURD] Move {} does not match pushing of the stage.",
+                index + 1
+            ),
+            Error::NotSolved => write!(f, "[LURD] The stage is not solved."),
+            Error::InvalidSave => write!(f, "[Save] The saved game is invalid."),
+            Error::InvalidTheme { line } => write!(f, "[Theme] Invalid style at line {}.", line),
+            Error::GlyphWidthMismatch => {
+                write!(f, "[Theme] Glyphs have to be the same width.")
+            }
+            Error::InvalidGeneratorOptions => write!(
+                f,
+                "[Generator] Width and height have to be at least 5 with at most {} squares, and balls at least 1 and fewer than a third of the squares inside the walls.",
+                MAX_SQUARES
+            ),
+            Error::GenerationFailed => write!(
+                f,
+                "[Generator] Cannot generate a solvable stage. Try other options."
+            ),
+            Error::LimitReached => write!(f, "[Solver] The search is stopped by its limits."),
+            Error::StageTooLarge { squares, max } => write!(
+                f,
+                "[Bitboard] Stage has {} squares, which is more than {}.",
+                squares, max
+            ),
+            Error::InvalidInput => write!(f, "Invalid input."),
+            Error::EmptyInput => write!(f, "Empty input."),
+            Error::InvalidLevelNumber => write!(f, "Invalid level number."),
+            Error::Canceled => write!(f, "Canceled."),
+            Error::Terminal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,137 @@
+//! Stage file formats for pusher
+//!
+//! Pusher reads its own alphabet and the standard XSB (Sokoban) notation.
+
+use super::error::Error;
+use super::stage::{ObjectType, Tile, BALL_CHR, EMPTY_CHR, GOAL_CHR, PLAYER_CHR, WALL_CHR};
+use std::fmt;
+use std::str::FromStr;
+
+/// Characters which only appear in XSB stages.
+const XSB_ONLY_CHRS: [char; 5] = ['$', '*', ' ', '-', '_'];
+
+/// Describes format of a stage file.
+/// * `Pusher`: `#` wall, `.` empty, `O` ball, `+` goal, `@` player
+/// * `Xsb`: `#` wall, ` `(or `-`, `_`) empty, `$` ball, `.` goal, `@` player,
+///   `*` ball on goal, `+` player on goal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageFormat {
+    Pusher,
+    Xsb,
+}
+
+impl StageFormat {
+    /// Guesses format of the stage.\
+    /// It returns `Xsb` when any character used only by XSB is found, else `Pusher`.
+    /// # Example
+    /// ```
+    /// use pusher::format::StageFormat;
+    /// assert_eq!(StageFormat::detect("#@O+#"), StageFormat::Pusher);
+    /// assert_eq!(StageFormat::detect("#@ $.#"), StageFormat::Xsb);
+    /// ```
+    pub fn detect(string: &str) -> StageFormat {
+        if string.contains(&XSB_ONLY_CHRS[..]) {
+            StageFormat::Xsb
+        } else {
+            StageFormat::Pusher
+        }
+    }
+    /// Converts a character to a Tile.\
+    /// It returns `None` if the character is not a tile of the format.
+    pub fn parse_char(self, c: char) -> Option<Tile> {
+        match self {
+            StageFormat::Pusher => match c {
+                WALL_CHR => Some(Tile::Wall),
+                EMPTY_CHR => Some(Tile::Plain(ObjectType::Empty)),
+                BALL_CHR => Some(Tile::Plain(ObjectType::Ball)),
+                GOAL_CHR => Some(Tile::Goal(ObjectType::Empty)),
+                PLAYER_CHR => Some(Tile::Plain(ObjectType::Player)),
+                _ => None,
+            },
+            StageFormat::Xsb => match c {
+                '#' => Some(Tile::Wall),
+                ' ' | '-' | '_' => Some(Tile::Plain(ObjectType::Empty)),
+                '$' => Some(Tile::Plain(ObjectType::Ball)),
+                '.' => Some(Tile::Goal(ObjectType::Empty)),
+                '@' => Some(Tile::Plain(ObjectType::Player)),
+                '*' => Some(Tile::Goal(ObjectType::Ball)),
+                '+' => Some(Tile::Goal(ObjectType::Player)),
+                _ => None,
+            },
+        }
+    }
+    /// Converts a Tile to a character.\
+    /// It returns `None` if the format cannot represent the tile.
+    pub fn tile_char(self, tile: &Tile) -> Option<char> {
+        match self {
+            StageFormat::Pusher => match tile {
+                Tile::Wall => Some(WALL_CHR),
+                Tile::Plain(ObjectType::Empty) => Some(EMPTY_CHR),
+                Tile::Plain(ObjectType::Ball) => Some(BALL_CHR),
+                Tile::Plain(ObjectType::Player) => Some(PLAYER_CHR),
+                Tile::Goal(ObjectType::Empty) => Some(GOAL_CHR),
+                Tile::Goal(_) | Tile::Outside => None,
+            },
+            StageFormat::Xsb => match tile {
+                Tile::Wall => Some('#'),
+                Tile::Plain(ObjectType::Empty) | Tile::Outside => Some(' '),
+                Tile::Plain(ObjectType::Ball) => Some('$'),
+                Tile::Plain(ObjectType::Player) => Some('@'),
+                Tile::Goal(ObjectType::Empty) => Some('.'),
+                Tile::Goal(ObjectType::Ball) => Some('*'),
+                Tile::Goal(ObjectType::Player) => Some('+'),
+            },
+        }
+    }
+}
+
+impl FromStr for StageFormat {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pusher" => Ok(StageFormat::Pusher),
+            "xsb" | "sok" | "sokoban" => Ok(StageFormat::Xsb),
+            _ => Err(Error::UnknownFormat(String::from(s))),
+        }
+    }
+}
+
+impl fmt::Display for StageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StageFormat::Pusher => write!(f, "pusher"),
+            StageFormat::Xsb => write!(f, "xsb"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn detect() {
+        assert_eq!(StageFormat::detect("#.@O+#"), StageFormat::Pusher);
+        assert_eq!(StageFormat::detect("#-@*#"), StageFormat::Xsb);
+        assert_eq!(StageFormat::detect("# @$.#"), StageFormat::Xsb);
+    }
+    #[test]
+    fn round_trip() {
+        for format in &[StageFormat::Pusher, StageFormat::Xsb] {
+            for c in "#.O+@ $*-_".chars() {
+                if let Some(tile) = format.parse_char(c) {
+                    let back = format.tile_char(&tile).unwrap();
+                    assert_eq!(format.parse_char(back), Some(tile));
+                }
+            }
+        }
+    }
+    #[test]
+    fn from_str() {
+        assert_eq!("XSB".parse(), Ok(StageFormat::Xsb));
+        assert_eq!("pusher".parse(), Ok(StageFormat::Pusher));
+        assert_eq!(
+            "png".parse::<StageFormat>(),
+            Err(Error::UnknownFormat(String::from("png")))
+        );
+    }
+}
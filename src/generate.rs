@@ -0,0 +1,274 @@
+//! Level generator for pusher
+//!
+//! It builds a random room from 3x3 templates, puts balls on goals,
+//! and scatters the balls by random pulls in reverse mode.
+//! Because every pull can be undone by a push, the generated stage is solvable,
+//! and A* verifies it again before it is emitted.
+//! A candidate which A* cannot solve within `CHECK_LIMITS` is dropped.
+
+use super::astar::{self, Limits, Outcome};
+use super::bitboard::MAX_SQUARES;
+use super::error::Error;
+use super::format::StageFormat;
+use super::stage::{flood, Direction, ObjectType, Stage, Tile};
+
+/// Templates of 3x3 blocks which the room is built from. `#` is a wall, and `.` is a floor.
+const TEMPLATES: [&str; 8] = [
+    ".........",
+    "#........",
+    "##.......",
+    "###......",
+    "....#....",
+    "#..#.....",
+    ".#..#....",
+    "#.#......",
+];
+
+/// How many rooms to try before giving up.
+const ATTEMPTS: usize = 100;
+
+/// Limits of A* which verifies a candidate.
+/// It has no time limit, so the same options make the same stage on any machine.
+const CHECK_LIMITS: Limits = Limits {
+    nodes: Some(20_000),
+    time: None,
+};
+
+/// Describes options of a generated stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// Seed of the random numbers, which makes the same stage
+    pub seed: u64,
+    /// Width of the stage including walls
+    pub width: usize,
+    /// Height of the stage including walls
+    pub height: usize,
+    /// Number of balls
+    pub balls: usize,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            seed: 0,
+            width: 8,
+            height: 8,
+            balls: 2,
+        }
+    }
+}
+
+/// Pseudo random number generator (SplitMix64)
+struct Rng(u64);
+
+impl Rng {
+    /// Returns the next random number.
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Returns a random number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Generates a solvable stage in pusher format, or XSB format if a ball is left on a goal.
+/// The same options make the same stage.
+/// # Example
+/// ```
+/// use pusher::generate::{generate, Options};
+/// use pusher::Stage;
+/// let options = Options { seed: 42, width: 7, height: 7, balls: 2 };
+/// let stage = generate(&options).unwrap();
+/// assert_eq!(generate(&options).unwrap(), stage);
+/// assert!(!Stage::new(&stage).unwrap().is_won());
+/// ```
+/// # Errors
+/// It returns `Err(Error)` when:
+/// * Width or height is less than 5, the stage has more than `bitboard::MAX_SQUARES` squares,
+///   there is no ball, or the inside of the walls is too small for the balls. (`Error::InvalidGeneratorOptions`)
+/// * No solvable stage is found in the attempts. (`Error::GenerationFailed`)
+pub fn generate(options: &Options) -> Result<String, Error> {
+    if options.width < 5 || options.height < 5 || options.balls == 0 {
+        return Err(Error::InvalidGeneratorOptions);
+    }
+    match options.width.checked_mul(options.height) {
+        Some(squares) if squares <= MAX_SQUARES => (),
+        _ => return Err(Error::InvalidGeneratorOptions),
+    }
+    // A candidate needs three floors a ball and one for the player, as below.
+    let inside = (options.width - 2) * (options.height - 2);
+    match options.balls.checked_mul(3) {
+        Some(floors) if floors < inside => (),
+        _ => return Err(Error::InvalidGeneratorOptions),
+    }
+    let mut rng = Rng(options.seed);
+    for _ in 0..ATTEMPTS {
+        let room = build_room(options, &mut rng);
+        let mut floors: Vec<usize> = (0..room.len()).filter(|&i| !room[i]).collect();
+        if floors.len() < options.balls * 3 + 1 {
+            continue;
+        }
+        for i in 0..options.balls + 1 {
+            let j = i + rng.below(floors.len() - i);
+            floors.swap(i, j);
+        }
+        let mut s = match solved_stage(options, &room, &floors[..options.balls + 1]) {
+            Some(s) => s.reversed(),
+            None => continue,
+        };
+        for _ in 0..floors.len() * options.balls * 4 {
+            let _ = s.move_player(Direction::ALL[rng.below(Direction::ALL.len())]);
+        }
+        let string = match s.to_format_string(StageFormat::Pusher) {
+            Ok(string) => string,
+            Err(_) => s.to_format_string(StageFormat::Xsb)?,
+        };
+        let stage = Stage::new(&string)?;
+        if stage.is_won() || stage.is_deadlocked() {
+            continue;
+        }
+        match astar::solve(&stage, CHECK_LIMITS)?.0 {
+            Outcome::Solved(_) => return Ok(string),
+            Outcome::Unsolvable | Outcome::LimitReached => (),
+        }
+    }
+    Err(Error::GenerationFailed)
+}
+
+/// Builds walls of a room by stamping random templates, and keeps the largest area only.
+/// # Returns
+/// Whether each tile is a wall
+fn build_room(options: &Options, rng: &mut Rng) -> Vec<bool> {
+    let (width, height) = (options.width, options.height);
+    let mut walls = vec![true; width * height];
+    for block_y in (1..height - 1).step_by(3) {
+        for block_x in (1..width - 1).step_by(3) {
+            let template = TEMPLATES[rng.below(TEMPLATES.len())].as_bytes();
+            let rotation = rng.below(4);
+            for (i, &c) in template.iter().enumerate() {
+                let (dx, dy) = rotate(i % 3, i / 3, rotation);
+                let (x, y) = (block_x + dx, block_y + dy);
+                if x < width - 1 && y < height - 1 {
+                    walls[x + y * width] = c == b'#';
+                }
+            }
+        }
+    }
+    let mut largest = Vec::new();
+    let mut visited = walls.clone();
+    for start in 0..walls.len() {
+        if visited[start] {
+            continue;
+        }
+        let area = flood(Some(start), width, walls.len(), |pos| !walls[pos]);
+        for &pos in &area {
+            visited[pos] = true;
+        }
+        if area.len() > largest.len() {
+            largest = area;
+        }
+    }
+    let mut room = vec![true; walls.len()];
+    for pos in largest {
+        room[pos] = false;
+    }
+    room
+}
+
+/// Rotates a position in a 3x3 block by 90 degrees `rotation` times.
+fn rotate(x: usize, y: usize, rotation: usize) -> (usize, usize) {
+    (0..rotation).fold((x, y), |(x, y), _| (2 - y, x))
+}
+
+/// Makes the won stage which has balls on `places[1..]` and the player on `places[0]`.
+fn solved_stage(options: &Options, room: &[bool], places: &[usize]) -> Option<Stage> {
+    let mut tiles: Vec<Tile> = room
+        .iter()
+        .map(|&wall| {
+            if wall {
+                Tile::Wall
+            } else {
+                Tile::Plain(ObjectType::Empty)
+            }
+        })
+        .collect();
+    tiles[places[0]] = Tile::Plain(ObjectType::Player);
+    for &ball in &places[1..] {
+        tiles[ball] = Tile::Goal(ObjectType::Ball);
+    }
+    let mut string = String::with_capacity((options.width + 1) * options.height);
+    for (index, tile) in tiles.iter().enumerate() {
+        string.push(StageFormat::Xsb.tile_char(tile)?);
+        if index % options.width == options.width - 1 {
+            string.push('\n');
+        }
+    }
+    Stage::with_format(&string, StageFormat::Xsb).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::solver::{self, SolveMode};
+    #[test]
+    fn reproducible() {
+        for seed in 0..5 {
+            let options = Options {
+                seed,
+                ..Options::default()
+            };
+            let string = generate(&options).unwrap();
+            assert_eq!(generate(&options).unwrap(), string);
+            let stage = Stage::new(&string).unwrap();
+            assert_eq!((stage.width(), stage.height()), (8, 8));
+            let balls = stage
+                .tiles()
+                .filter(|(_, tile)| tile.object() == Some(ObjectType::Ball))
+                .count();
+            assert_eq!(balls, 2);
+            assert!(solver::solve(&stage, SolveMode::Pushes).is_some());
+        }
+    }
+    #[test]
+    fn invalid_options() {
+        let options = Options {
+            width: 4,
+            ..Options::default()
+        };
+        assert_eq!(generate(&options), Err(Error::InvalidGeneratorOptions));
+        let options = Options {
+            balls: 0,
+            ..Options::default()
+        };
+        assert_eq!(generate(&options), Err(Error::InvalidGeneratorOptions));
+        let options = Options {
+            width: 130,
+            height: 130,
+            ..Options::default()
+        };
+        assert_eq!(generate(&options), Err(Error::InvalidGeneratorOptions));
+        let options = Options {
+            width: usize::MAX,
+            height: 5,
+            ..Options::default()
+        };
+        assert_eq!(generate(&options), Err(Error::InvalidGeneratorOptions));
+        let options = Options {
+            width: 5,
+            height: 5,
+            balls: 3,
+            ..Options::default()
+        };
+        assert_eq!(generate(&options), Err(Error::InvalidGeneratorOptions));
+        let options = Options {
+            balls: usize::MAX,
+            ..Options::default()
+        };
+        assert_eq!(generate(&options), Err(Error::InvalidGeneratorOptions));
+    }
+}
@@ -1,39 +1,136 @@
 //! Simple CLI clone of sokoban
+//!
+//! The game engine can be used without the terminal loop.
+//! # Example
+//! ```
+//! use pusher::{Direction, ObjectType, Stage, Tile, Vector2};
+//! let mut stage = Stage::new("#@.O+#").unwrap();
+//! assert_eq!((stage.width(), stage.height()), (6, 1));
+//! stage.move_player(Direction::Right).unwrap();
+//! stage.move_player(Direction::Right).unwrap();
+//! assert_eq!(stage.player_position(), Vector2::new(3, 0));
+//! assert_eq!(stage.tile(Vector2::new(4, 0)), Some(&Tile::Goal(ObjectType::Ball)));
+//! assert_eq!(stage.matched_goals(), stage.total_goals());
+//! assert!(stage.is_won());
+//! ```
 
 #[cfg(feature = "tui")]
 use crossterm::{
     cursor::MoveTo,
-    event::{read, Event, KeyCode},
+    event::{
+        poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton,
+        MouseEvent,
+    },
     execute,
     style::Print,
     terminal::{
-        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
         LeaveAlternateScreen, SetTitle,
     },
 };
-use std::error;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
+pub mod astar;
+pub mod bitboard;
+pub mod collection;
+mod deadlock;
+pub mod env;
+pub mod error;
+pub mod format;
+pub mod generate;
+pub mod lurd;
+pub mod path;
+pub mod playback;
+pub mod rating;
+pub mod save;
+pub mod solver;
 mod stage;
+pub mod theme;
+pub mod validate;
 mod vector2;
+pub mod viewport;
+pub mod zobrist;
 
-use stage::{Direction, Stage};
+use collection::Collection;
+pub use error::Error;
+use format::StageFormat;
+use playback::Playback;
+use save::SaveGame;
+use solver::Hint;
+pub use stage::{Direction, Move, ObjectType, Stage, Tile};
+use theme::Theme;
 pub use vector2::Vector2;
+use viewport::Viewport;
+
+/// (TUI) Rows below the stage: status, help and message.
+#[cfg(feature = "tui")]
+const RESERVED_ROWS: usize = 5;
+/// (TUI) Delays between moves of a replay in milliseconds, from the slowest speed
+#[cfg(feature = "tui")]
+const REPLAY_DELAYS: [u64; 6] = [800, 400, 200, 100, 50, 25];
+/// (TUI) Index of the default speed in `REPLAY_DELAYS`
+#[cfg(feature = "tui")]
+const DEFAULT_SPEED: usize = 3;
+/// Limits of the search for a hint, which keep the game responsive
+const HINT_LIMITS: astar::Limits = astar::Limits {
+    nodes: Some(100_000),
+    time: Some(std::time::Duration::from_secs(2)),
+};
+/// Help of keys while playing
+const PLAY_HELP: &str =
+    "WASD to move, U to undo, R to redo, I for hint, N/P/G to change level, V to save, Q to quit.";
+/// Help of keys while replaying
+const REPLAY_HELP: &str =
+    "Space to pause, A/D to step, W/S to change speed, Home/End/G to jump, Q to quit.";
 /// Struct for contain parsed arguments.
 pub struct Arguments {
     /// File name of stage data
     pub filename: String,
+    /// Format of stage data, `None` to detect automatically
+    pub format: Option<StageFormat>,
+    /// Number of level to play first (1-based)
+    pub level: usize,
+    /// File name of LURD solution to replay, `None` to play
+    pub replay: Option<String>,
+    /// LURD solution to replay, which is used instead of `replay`
+    pub moves: Option<String>,
+    /// Name of built-in theme or file name of theme, `None` for default theme
+    pub theme: Option<String>,
+    /// Whether to play in reverse mode, where the player pulls balls
+    pub reverse: bool,
+}
+
+/// Describes what to do after handling input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// Keep playing the level
+    Continue,
+    /// Quit the game
+    Quit,
+    /// Play the next level
+    Next,
+    /// Play the previous level
+    Previous,
+    /// Play the level of the number (1-based)
+    Select(usize),
+    /// Save the game
+    Save,
+    /// Show the next push
+    Hint,
+    /// (TUI) Left button is clicked on the screen position (column, row)
+    #[cfg(feature = "tui")]
+    Click(u16, u16),
 }
 
 /// Handles input and update stage.
 /// # Returns
-/// `true` if user wants to continue, else `false`
+/// `Action` which user wants
 /// # Errors
-/// It returns `Err(&'static str)` when:
+/// It returns `Err(Error)` when:
 /// * Error occured while moving player
 /// * Input is invalid or empty
-fn update(s: &mut Stage) -> Result<bool, &'static str> {
+fn update(s: &mut Stage) -> Result<Action, Error> {
     #[cfg(not(feature = "tui"))]
     {
         let mut inputs = String::new();
@@ -44,47 +141,196 @@ fn update(s: &mut Stage) -> Result<bool, &'static str> {
         let input = inputs.trim().chars().next();
         match input {
             Some(cmd) => match cmd {
-                'W' | 'w' | 'K' | 'k' => s.move_player(Direction::Up).map(|()| true),
-                'A' | 'a' | 'H' | 'h' => s.move_player(Direction::Left).map(|()| true),
-                'S' | 's' | 'J' | 'j' => s.move_player(Direction::Down).map(|()| true),
-                'D' | 'd' | 'L' | 'l' => s.move_player(Direction::Right).map(|()| true),
-                'Q' | 'q' => Ok(false),
-                _ => Err("Invalid input."),
+                'W' | 'w' | 'K' | 'k' => s.move_player(Direction::Up).map(|()| Action::Continue),
+                'A' | 'a' | 'H' | 'h' => s.move_player(Direction::Left).map(|()| Action::Continue),
+                'S' | 's' | 'J' | 'j' => s.move_player(Direction::Down).map(|()| Action::Continue),
+                'D' | 'd' | 'L' | 'l' => s.move_player(Direction::Right).map(|()| Action::Continue),
+                'U' | 'u' => s.undo().map(|()| Action::Continue),
+                'R' | 'r' => s.redo().map(|()| Action::Continue),
+                'N' | 'n' => Ok(Action::Next),
+                'P' | 'p' => Ok(Action::Previous),
+                'G' | 'g' => inputs.trim()[1..]
+                    .trim()
+                    .parse()
+                    .map(Action::Select)
+                    .map_err(|_err| Error::InvalidLevelNumber),
+                'V' | 'v' => Ok(Action::Save),
+                'I' | 'i' => Ok(Action::Hint),
+                'Q' | 'q' => Ok(Action::Quit),
+                _ => Err(Error::InvalidInput),
             },
-            None => Err("Empty input."),
+            None => Err(Error::EmptyInput),
         }
     }
     #[cfg(feature = "tui")]
     {
-        let input = read().map_err(|_err| "Invalid input")?;
-        if let Event::Key(event) = input {
-            match event.code {
+        match read().map_err(|_err| Error::InvalidInput)? {
+            Event::Mouse(MouseEvent::Down(MouseButton::Left, column, row, _)) => {
+                Ok(Action::Click(column, row))
+            }
+            Event::Key(event) => match event.code {
                 KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('w') => {
-                    s.move_player(Direction::Up).map(|()| true)
+                    s.move_player(Direction::Up).map(|()| Action::Continue)
                 }
                 KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('s') => {
-                    s.move_player(Direction::Down).map(|()| true)
+                    s.move_player(Direction::Down).map(|()| Action::Continue)
                 }
                 KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('a') => {
-                    s.move_player(Direction::Left).map(|()| true)
+                    s.move_player(Direction::Left).map(|()| Action::Continue)
                 }
                 KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('d') => {
-                    s.move_player(Direction::Right).map(|()| true)
+                    s.move_player(Direction::Right).map(|()| Action::Continue)
+                }
+                KeyCode::Backspace | KeyCode::Char('u') => s.undo().map(|()| Action::Continue),
+                KeyCode::Char('r') => s.redo().map(|()| Action::Continue),
+                KeyCode::Char('n') => Ok(Action::Next),
+                KeyCode::Char('p') => Ok(Action::Previous),
+                KeyCode::Char('g') => read_digits("Level: ").and_then(|digits| {
+                    digits
+                        .parse()
+                        .map(Action::Select)
+                        .map_err(|_err| Error::InvalidLevelNumber)
+                }),
+                KeyCode::Char('v') => Ok(Action::Save),
+                KeyCode::Char('i') => Ok(Action::Hint),
+                KeyCode::Esc | KeyCode::Char('q') => Ok(Action::Quit),
+                _ => Ok(Action::Continue),
+            },
+            // Stage is rendered again with the viewport of new size.
+            Event::Resize(_, _) | Event::Mouse(_) => Ok(Action::Continue),
+        }
+    }
+}
+
+/// (TUI) Prints `prompt` and reads digits until Enter is pressed.
+/// # Errors
+/// It returns `Err(Error::Canceled)` when input is canceled by Esc.
+#[cfg(feature = "tui")]
+fn read_digits(prompt: &str) -> Result<String, Error> {
+    let mut digits = String::new();
+    execute!(io::stdout(), Print(prompt)).map_err(|_err| Error::Terminal("Failed to Print."))?;
+    loop {
+        if let Event::Key(event) = read().map_err(|_err| Error::InvalidInput)? {
+            match event.code {
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    digits.push(c);
+                    execute!(io::stdout(), Print(c))
+                        .map_err(|_err| Error::Terminal("Failed to Print."))?;
                 }
-                KeyCode::Esc | KeyCode::Char('q') => Ok(false),
-                _ => Ok(true),
+                KeyCode::Backspace if digits.pop().is_some() => {
+                    execute!(io::stdout(), Print("\x08 \x08"))
+                        .map_err(|_err| Error::Terminal("Failed to Print."))?;
+                }
+                KeyCode::Enter => break,
+                KeyCode::Esc => return Err(Error::Canceled),
+                _ => (),
             }
-        } else {
-            Ok(true)
         }
     }
+    Ok(digits)
+}
+
+/// (TUI) Handles a click on the stage position `pos`.\
+/// Clicking an empty tile walks the player there.
+/// Clicking a ball selects it, and clicking a destination next pushes the selected ball there.
+/// # Returns
+/// Message which describes the selection
+/// # Errors
+/// It returns `Err(Error::Unreachable)` when there is no path to the destination.
+#[cfg(feature = "tui")]
+fn click(s: &mut Stage, selected: &mut Option<Vector2>, pos: Vector2) -> Result<String, Error> {
+    let path = match (selected.take(), s.tile(pos).and_then(Tile::object)) {
+        (Some(ball), _) if ball == pos => return Ok(String::new()),
+        (Some(ball), Some(_)) => path::push_path(s, ball, pos),
+        (None, Some(ObjectType::Ball)) => {
+            *selected = Some(pos);
+            return Ok(format!(
+                "Ball at {} is selected. Click the destination.\r\n",
+                pos
+            ));
+        }
+        (None, Some(_)) => path::walk_path(s, pos),
+        (_, None) => return Ok(String::new()),
+    };
+    for direction in path.ok_or(Error::Unreachable)? {
+        s.move_player(direction)?;
+    }
+    Ok(String::new())
 }
 
-/// Render Stage `s` and additional message `msg`.
+/// Asks `question` and reads yes or no.
+/// # Returns
+/// `true` if the answer starts with 'y'
+/// # Errors
+/// (TUI) It returns `Err(Error::Terminal)` when printing or reading failed.
+fn confirm(question: &str) -> Result<bool, Error> {
+    #[cfg(not(feature = "tui"))]
+    {
+        println!("{} (y/n)", question);
+        let mut inputs = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut inputs)
+            .expect("Failed to read input.");
+        Ok(matches!(
+            inputs.trim().chars().next(),
+            Some('Y') | Some('y')
+        ))
+    }
+    #[cfg(feature = "tui")]
+    {
+        execute!(
+            io::stdout(),
+            Clear(ClearType::All),
+            MoveTo(0, 0),
+            Print(format!("{} (y/n)\r\n", question))
+        )
+        .map_err(|_err| Error::Terminal("Failed to Print."))?;
+        loop {
+            if let Event::Key(event) = read().map_err(|_err| Error::InvalidInput)? {
+                match event.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => return Ok(true),
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => return Ok(false),
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// Makes a viewport of Stage `s` in `theme` which fits in the terminal with `header`.\
+/// Without TUI, it shows the whole stage.
+#[cfg_attr(not(feature = "tui"), allow(unused_variables))]
+fn viewport(s: &Stage, theme: &Theme, header: &str) -> Viewport {
+    #[cfg(feature = "tui")]
+    if let Ok((columns, rows)) = size() {
+        let rows = (rows as usize).saturating_sub(header.lines().count() + RESERVED_ROWS);
+        return Viewport::centered(s, columns as usize / theme.width(), rows);
+    }
+    Viewport::full(s)
+}
+
+/// Render Stage `s` in `theme` with `header`, `help` of keys and additional message `msg`.\
+/// The ball of `hint` is marked with the direction to push.
+/// (TUI) Only the viewport around the player is rendered.
 /// # Errors
 /// (TUI) It return `Err` when rendering failed.
-fn render(s: &mut Stage, msg: &str) -> Result<(), &'static str> {
-    let stage_string = format!("{}WASD to move, Q to quit.\r\n{}", s, msg);
+fn render(
+    s: &Stage,
+    theme: &Theme,
+    hint: Option<&Hint>,
+    header: &str,
+    help: &str,
+    msg: &str,
+) -> Result<(), Error> {
+    let stage_string = format!(
+        "{}{}{}{}\r\n{}",
+        header,
+        viewport(s, theme, header).render(s, theme, hint),
+        s.status(),
+        help,
+        msg
+    );
     #[cfg(not(feature = "tui"))]
     {
         println!("{}", stage_string);
@@ -99,80 +345,730 @@ fn render(s: &mut Stage, msg: &str) -> Result<(), &'static str> {
             Print(stage_string)
         ) {
             Ok(_) => Ok(()),
-            Err(_) => Err("Failed to render stage."),
+            Err(_) => Err(Error::Terminal("Failed to render stage.")),
         }
     }
 }
 
-/// Runs the game. It loads stage and interpret command.
+/// Makes a header which describes the level.\
+/// It is empty for a collection of a nameless level.
+fn level_header(collection: &Collection, index: usize, reverse: bool) -> String {
+    let level = &collection.levels()[index];
+    if collection.len() == 1 && level.title.is_none() && !reverse {
+        return String::new();
+    }
+    let mut header = format!("Level {}/{}", index + 1, collection.len());
+    if reverse {
+        header.push_str(" (Reverse)");
+    }
+    if let Some(title) = &level.title {
+        header.push_str(": ");
+        header.push_str(title);
+    }
+    if let Some(author) = &level.author {
+        header.push_str(" by ");
+        header.push_str(author);
+    }
+    header.push_str("\r\n");
+    header
+}
+
+/// Makes a line of `msg` in `color`, which is reset at the end.
+fn colored_line(color: Option<&str>, msg: &str) -> String {
+    match color {
+        Some(color) => format!("\x1b[{}m{}\x1b[0m\r\n", color, msg),
+        None => format!("{}\r\n", msg),
+    }
+}
+
+/// Makes error message in the error color of `theme`.
+fn error_message(theme: &Theme, err: &Error) -> String {
+    colored_line(theme.error.as_deref(), &format!("Error: {}", err))
+}
+
+/// Makes warning message in the warning color of `theme`.
+fn warning_message(theme: &Theme, msg: &str) -> String {
+    colored_line(theme.warning.as_deref(), &format!("Warning: {}", msg))
+}
+
+/// Makes message for winning in the won color of `theme`.
+fn won_message(theme: &Theme, msg: &str) -> String {
+    colored_line(theme.won.as_deref(), msg)
+}
+
+/// Reads whole file.
 /// # Errors
-/// It returns `Err` when:
-/// * File is not found
-/// * Error is propagated from `Stage`
-/// * (TUI) Switch terminal screen is failed.
-/// # Panics
-/// It panics when `crossterm::excute` failed.
-pub fn run(args: Arguments) -> Result<(), Box<dyn error::Error>> {
-    //load
-    let fnf_msg = format!("File {} not found.\r\n", args.filename);
-    let mut f = File::open(args.filename).map_err(|err| {
+/// It returns `Err` when file is not found or cannot be read.
+fn read_file(filename: &str) -> io::Result<String> {
+    let mut f = File::open(filename).map_err(|err| {
         if matches!(err.kind(), io::ErrorKind::NotFound) {
-            io::Error::new(io::ErrorKind::NotFound, fnf_msg)
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("File {} not found.\r\n", filename),
+            )
         } else {
             err
         }
     })?;
-
     let mut contents = String::new();
     f.read_to_string(&mut contents)?;
-    //switch screen
+    Ok(contents)
+}
+
+/// Reads the file and parses levels in it.
+/// # Errors
+/// It returns `Err` if the file cannot be read or it has no level.
+fn load_collection(filename: &str) -> Result<Collection, Box<dyn std::error::Error>> {
+    Ok(Collection::parse(&read_file(filename)?)?)
+}
+
+/// Loads a built-in theme by `name`, or a theme file if it is not built-in.
+/// # Errors
+/// It returns `Err` when the file cannot be read or parsed.
+fn load_theme(name: &str) -> Result<Theme, Box<dyn std::error::Error>> {
+    match Theme::builtin(name) {
+        Some(theme) => Ok(theme),
+        None => Ok(Theme::parse(&read_file(name)?)?),
+    }
+}
+
+/// Makes file name of the solution of the level (0-based).
+fn solution_filename(filename: &str, level: usize, reverse: bool) -> String {
+    if reverse {
+        format!("{}.{}.reverse.lurd", filename, level + 1)
+    } else {
+        format!("{}.{}.lurd", filename, level + 1)
+    }
+}
+
+/// Makes the stage of the level (0-based), which is reversed in reverse mode.
+/// # Errors
+/// It returns `Err` propagated from `Level::stage`.
+fn level_stage(args: &Arguments, collection: &Collection, level: usize) -> Result<Stage, Error> {
+    let s = collection.levels()[level].stage(args.format)?;
+    Ok(if args.reverse { s.reversed() } else { s })
+}
+
+/// Makes file name of the saved game.
+fn save_filename(filename: &str) -> String {
+    format!("{}.sav", filename)
+}
+
+/// Saves the stage of the level (0-based) to `path`.
+/// # Returns
+/// Message which describes the result in `theme`
+fn save_game(theme: &Theme, path: &str, level: usize, s: &Stage) -> String {
+    let result = SaveGame::new(level + 1, s)
+        .map_err(|err| err.to_string())
+        .and_then(|save| std::fs::write(path, save.to_string()).map_err(|err| err.to_string()));
+    match result {
+        Ok(()) => format!("Game is saved to {}.\r\n", path),
+        Err(err) => warning_message(theme, &format!("Cannot save game to {}: {}", path, err)),
+    }
+}
+
+/// (TUI) Switches to alternate screen and enables raw mode.
+/// # Errors
+/// (TUI) It returns `Err(Error::Terminal)` when switching screen failed.
+fn enter_screen() -> Result<(), Error> {
     #[cfg(feature = "tui")]
     {
         execute!(
             io::stdout(),
             EnterAlternateScreen,
             Clear(ClearType::All),
-            SetTitle("Pusher")
+            SetTitle("Pusher"),
+            EnableMouseCapture
         )
-        .map_err(|_err| "Cannot switch screen.")?;
-        enable_raw_mode().map_err(|_err| "Cannot enable raw mode.")?;
+        .map_err(|_err| Error::Terminal("Cannot switch screen."))?;
+        enable_raw_mode().map_err(|_err| Error::Terminal("Cannot enable raw mode."))?;
     }
-    //setup stage
-    let mut s = stage::Stage::new(&contents)?;
-    let mut message = String::new();
-    //update
-    loop {
-        render(&mut s, &message)?;
-        message.clear();
-        match update(&mut s) {
-            Ok(true) => (),
-            Ok(false) => break,
-            Err(msg) => {
-                message = if cfg!(feature = "color") {
-                    format!("\x1b[0;31mError: {}\x1b[0m\r\n", msg)
+    Ok(())
+}
+
+/// (TUI) Waits for a key and returns to original screen.
+/// # Errors
+/// (TUI) It returns `Err(Error::Terminal)` when switching screen failed.
+fn leave_screen() -> Result<(), Error> {
+    #[cfg(feature = "tui")]
+    {
+        execute!(io::stdout(), Print("\r\n[Press any key to quit].\r\n"))
+            .map_err(|_err| Error::Terminal("Failed to Print."))?;
+        read().map_err(|_err| Error::Terminal("Cannot read event."))?; //Wait for input.
+        disable_raw_mode().map_err(|_err| Error::Terminal("Cannot disable raw mode."))?;
+        execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen)
+            .map_err(|_err| Error::Terminal("Cannot return to original screen."))?;
+    }
+    Ok(())
+}
+
+/// Runs the game. It loads collection of stages and interpret command.\
+/// If `args.moves` or `args.replay` is set, it replays the solution instead.
+/// # Errors
+/// It returns `Err` when:
+/// * File is not found
+/// * Theme is invalid
+/// * Level number is out of range
+/// * Error is propagated from `Collection` or `Stage`
+/// * Replayed solution does not solve the level
+/// * (TUI) Switch terminal screen is failed.
+/// # Panics
+/// It panics when `crossterm::excute` failed.
+pub fn run(args: Arguments) -> Result<(), Box<dyn std::error::Error>> {
+    //load
+    let collection = load_collection(&args.filename)?;
+    if args.level == 0 || args.level > collection.len() {
+        return Err(Error::LevelOutOfRange {
+            level: args.level,
+            count: collection.len(),
+        }
+        .into());
+    }
+    let theme = match &args.theme {
+        Some(theme) => load_theme(theme)?,
+        None => Theme::default(),
+    };
+    let solution = match (&args.moves, &args.replay) {
+        (Some(moves), _) => Some(moves.clone()),
+        (None, Some(replay)) => Some(read_file(replay)?),
+        (None, None) => None,
+    };
+    //switch screen
+    enter_screen()?;
+    let result = match solution {
+        Some(solution) => replay(&args, &collection, &theme, &solution),
+        None => play(&args, &collection, &theme),
+    };
+    leave_screen()?;
+    result
+}
+
+/// Validates every level of `files` and prints issues with their file names.\
+/// The format is detected for each level if `format` is `None`.
+/// # Returns
+/// `true` if no error is found. Warnings do not fail validation.
+pub fn validate_files(files: &[String], format: Option<StageFormat>) -> bool {
+    let mut valid = true;
+    for filename in files {
+        let collection = load_collection(filename);
+        let collection = match collection {
+            Ok(collection) => collection,
+            Err(err) => {
+                println!("{}: error: {}", filename, err.to_string().trim_end());
+                valid = false;
+                continue;
+            }
+        };
+        let (mut errors, mut warnings) = (0, 0);
+        for (index, level) in collection.levels().iter().enumerate() {
+            let format = format.unwrap_or_else(|| StageFormat::detect(&level.data));
+            for issue in validate::validate(&level.data, format, level.line) {
+                if issue.is_error() {
+                    errors += 1;
                 } else {
-                    format!("Error: {}", msg)
-                };
+                    warnings += 1;
+                }
+                println!("{}: level {}: {}", filename, index + 1, issue);
+            }
+        }
+        println!(
+            "{}: {} level(s), {} error(s), {} warning(s)",
+            filename,
+            collection.len(),
+            errors,
+            warnings
+        );
+        valid &= errors == 0;
+    }
+    valid
+}
+
+/// Rates every level of `files` by A* within `limits` and prints a table sorted from easy to hard.\
+/// Levels which reach the limits are unrated, and they are put before unsolvable levels at the end.
+/// The format is detected for each level if `format` is `None`.
+/// # Returns
+/// `true` if every file is read and every level is loaded.
+pub fn rate_files(files: &[String], format: Option<StageFormat>, limits: astar::Limits) -> bool {
+    let mut valid = true;
+    let mut rows = Vec::new();
+    for filename in files {
+        let collection = load_collection(filename);
+        let collection = match collection {
+            Ok(collection) => collection,
+            Err(err) => {
+                eprintln!("{}: error: {}", filename, err.to_string().trim_end());
+                valid = false;
+                continue;
             }
         };
-        if s.is_won() {
-            message = if cfg!(feature = "color") {
-                String::from("\x1b[0;33mYou Won!\x1b[0m\r\n")
-            } else {
-                String::from("You Won!\r\n")
+        for (index, level) in collection.levels().iter().enumerate() {
+            let name = match &level.title {
+                Some(title) => format!("{}:{} {}", filename, index + 1, title),
+                None => format!("{}:{}", filename, index + 1),
             };
-            render(&mut s, &message)?;
-            break;
+            match level
+                .stage(format)
+                .and_then(|stage| rating::rate(&stage, limits))
+            {
+                rating @ Ok(_) | rating @ Err(Error::LimitReached) => rows.push((name, rating)),
+                Err(err) => {
+                    eprintln!("{}: level {}: error: {}", filename, index + 1, err);
+                    valid = false;
+                }
+            }
         }
     }
-    #[cfg(feature = "tui")]
-    {
-        execute!(io::stdout(), Print("\r\n[Press any key to quit].\r\n"))
-            .map_err(|_err| "Failed to Print.")?;
-        read().map_err(|_err| "Cannot read event.")?; //Wait for input.
-        disable_raw_mode().map_err(|_err| "Cannot disable raw mode.")?;
-        execute!(io::stdout(), LeaveAlternateScreen)
-            .map_err(|_err| "Cannot return to original screen.")?;
+    let order = |rating: &Result<Option<rating::Rating>, Error>| match rating {
+        Ok(Some(r)) => (0, r.score()),
+        Err(_) => (1, 0.0),
+        Ok(None) => (2, 0.0),
+    };
+    rows.sort_by(|a, b| order(&a.1).partial_cmp(&order(&b.1)).unwrap());
+    println!(
+        "{:>4} {:>7} {:>6} {:>6} {:>8} {:>5} {:>9}  Level",
+        "Rank", "Score", "Pushes", "Moves", "Nodes", "Balls", "Branching"
+    );
+    for (rank, (name, rating)) in rows.iter().enumerate() {
+        match rating {
+            Ok(Some(r)) => println!(
+                "{:>4} {:>7.1} {:>6} {:>6} {:>8} {:>5} {:>9.2}  {}",
+                rank + 1,
+                r.score(),
+                r.pushes,
+                r.moves,
+                r.nodes,
+                r.balls,
+                r.branching,
+                name
+            ),
+            Ok(None) => println!(
+                "{:>4} {:>7} {:>6} {:>6} {:>8} {:>5} {:>9}  {} (unsolvable)",
+                rank + 1,
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                name
+            ),
+            Err(_) => println!(
+                "{:>4} {:>7} {:>6} {:>6} {:>8} {:>5} {:>9}  {} unrated (limit reached)",
+                rank + 1,
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                name
+            ),
+        }
+    }
+    valid
+}
+
+/// Struct for options of `solve_file`.
+pub struct SolveOptions {
+    /// Format of stage data, `None` to detect automatically
+    pub format: Option<StageFormat>,
+    /// Number of level to solve (1-based), `None` for every level
+    pub level: Option<usize>,
+    /// Number of threads of the move-optimal solver
+    pub threads: usize,
+    /// Whether to find push-optimal solutions by A* instead
+    pub informed: bool,
+    /// Limits of A*
+    pub limits: astar::Limits,
+}
+
+/// Solves levels of `filename` and prints solutions in LURD.\
+/// Solutions are move-optimal by the multi-threaded solver,
+/// or push-optimal by A* with `options.informed`, which also prints statistics to stderr.
+/// # Returns
+/// `true` if every level is loaded and solved.
+pub fn solve_file(filename: &str, options: &SolveOptions) -> bool {
+    let collection = load_collection(filename);
+    let collection = match collection {
+        Ok(collection) => collection,
+        Err(err) => {
+            eprintln!("{}: error: {}", filename, err.to_string().trim_end());
+            return false;
+        }
+    };
+    let levels = match options.level {
+        Some(level) if level == 0 || level > collection.len() => {
+            eprintln!(
+                "{}: error: {}",
+                filename,
+                Error::LevelOutOfRange {
+                    level,
+                    count: collection.len()
+                }
+            );
+            return false;
+        }
+        Some(level) => level - 1..level,
+        None => 0..collection.len(),
+    };
+    let mut solved = true;
+    for index in levels {
+        let stage = match collection.levels()[index].stage(options.format) {
+            Ok(stage) => stage,
+            Err(err) => {
+                eprintln!("{}: level {}: error: {}", filename, index + 1, err);
+                solved = false;
+                continue;
+            }
+        };
+        let solution = if options.informed {
+            let (outcome, statistics) = match astar::solve(&stage, options.limits) {
+                Ok(result) => result,
+                Err(err) => {
+                    eprintln!("{}: level {}: error: {}", filename, index + 1, err);
+                    solved = false;
+                    continue;
+                }
+            };
+            eprintln!(
+                "{}: level {}: {} state(s) expanded, {} generated, {:.3} s",
+                filename,
+                index + 1,
+                statistics.expanded,
+                statistics.generated,
+                statistics.elapsed.as_secs_f64()
+            );
+            match outcome {
+                astar::Outcome::Solved(solution) => Some(solution),
+                astar::Outcome::Unsolvable => None,
+                astar::Outcome::LimitReached => {
+                    println!("{}: level {}: limit reached", filename, index + 1);
+                    solved = false;
+                    continue;
+                }
+            }
+        } else {
+            solver::solve_parallel(&stage, options.threads)
+        };
+        match solution {
+            Some(solution) => {
+                let mut played = stage.clone();
+                let checked = solution
+                    .iter()
+                    .try_for_each(|&direction| played.move_player(direction))
+                    .and_then(|()| {
+                        if played.is_won() {
+                            Ok(())
+                        } else {
+                            Err(Error::NotSolved)
+                        }
+                    });
+                match checked {
+                    Ok(()) => println!(
+                        "{}: level {}: {}",
+                        filename,
+                        index + 1,
+                        lurd::to_string(played.history())
+                    ),
+                    Err(err) => {
+                        eprintln!(
+                            "{}: level {}: error: invalid solution: {}",
+                            filename,
+                            index + 1,
+                            err
+                        );
+                        solved = false;
+                    }
+                }
+            }
+            None => {
+                println!("{}: level {}: no solution", filename, index + 1);
+                solved = false;
+            }
+        }
+    }
+    solved
+}
+
+/// Plays levels of the collection from `args.level`.
+/// # Errors
+/// It returns `Err` propagated from `Stage` or rendering.
+fn play(
+    args: &Arguments,
+    collection: &Collection,
+    theme: &Theme,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut level = args.level - 1;
+    let save_path = save_filename(&args.filename);
+    let mut resumed = None;
+    let mut notice = String::new();
+    if let Ok(contents) = std::fs::read_to_string(&save_path) {
+        if confirm(&format!("Resume saved game in {}?", save_path))? {
+            let restored = SaveGame::parse(&contents)
+                .and_then(|save| Ok((save.level - 1, save.restore(collection, args.format)?)));
+            match restored {
+                Ok((saved_level, s)) => {
+                    level = saved_level;
+                    resumed = Some(s);
+                }
+                Err(err) => notice = error_message(theme, &err),
+            }
+        }
+    }
+    'levels: loop {
+        //setup stage
+        let mut s = match resumed.take() {
+            Some(s) => s,
+            None => level_stage(args, collection, level)?,
+        };
+        let header = level_header(collection, level, s.is_reverse());
+        let mut message = std::mem::take(&mut notice);
+        #[cfg(feature = "tui")]
+        let mut selected = None;
+        let mut hint = None;
+        //update
+        loop {
+            render(&s, theme, hint.as_ref(), &header, PLAY_HELP, &message)?;
+            message.clear();
+            hint = None;
+            let next_level = match update(&mut s) {
+                Ok(Action::Continue) => None,
+                Ok(Action::Quit) => {
+                    // A level without moves has nothing to resume, so an older save is stale.
+                    let message = if s.history().is_empty() {
+                        let _ = std::fs::remove_file(&save_path);
+                        String::new()
+                    } else {
+                        save_game(theme, &save_path, level, &s)
+                    };
+                    render(&s, theme, None, &header, PLAY_HELP, &message)?;
+                    break 'levels;
+                }
+                Ok(Action::Save) => {
+                    message = save_game(theme, &save_path, level, &s);
+                    None
+                }
+                Ok(Action::Hint) => {
+                    let result = solver::hint(&s, HINT_LIMITS);
+                    hint = result.clone().unwrap_or(None);
+                    message = match result {
+                        Ok(Some(hint)) => format!(
+                            "Hint: {} the ball at {} {}.\r\n",
+                            if s.is_reverse() { "Pull" } else { "Push" },
+                            hint.ball,
+                            format!("{:?}", hint.direction).to_lowercase()
+                        ),
+                        Ok(None) if s.is_won() => String::new(),
+                        Ok(None) => {
+                            warning_message(theme, "No solution from here. Press U to undo.")
+                        }
+                        Err(Error::LimitReached) => warning_message(
+                            theme,
+                            "Hint unavailable, the search has reached its limit.",
+                        ),
+                        Err(err) => error_message(theme, &err),
+                    };
+                    None
+                }
+                #[cfg(feature = "tui")]
+                Ok(Action::Click(column, row)) => {
+                    let pos = (row as usize)
+                        .checked_sub(header.lines().count())
+                        .and_then(|row| {
+                            viewport(&s, theme, &header)
+                                .to_stage(column as usize / theme.width(), row)
+                        });
+                    if let Some(pos) = pos {
+                        match click(&mut s, &mut selected, pos) {
+                            Ok(msg) => message = msg,
+                            Err(err) => message = error_message(theme, &err),
+                        }
+                    }
+                    None
+                }
+                Ok(Action::Next) => Some(level + 2),
+                Ok(Action::Previous) => Some(level),
+                Ok(Action::Select(number)) => Some(number),
+                Err(err) => {
+                    message = error_message(theme, &err);
+                    None
+                }
+            };
+            if let Some(number) = next_level {
+                if number >= 1 && number <= collection.len() {
+                    level = number - 1;
+                    continue 'levels;
+                }
+                message = error_message(
+                    theme,
+                    &Error::LevelOutOfRange {
+                        level: number,
+                        count: collection.len(),
+                    },
+                );
+            }
+            if s.is_deadlocked() && !s.is_won() {
+                message.push_str(&warning_message(
+                    theme,
+                    "Deadlock! A ball cannot reach a goal. Press U to undo.",
+                ));
+            }
+            if s.is_won() {
+                let _ = std::fs::remove_file(&save_path);
+                message = won_message(theme, &format!("You Won! {}", s.summary()));
+                let path = solution_filename(&args.filename, level, s.is_reverse());
+                match std::fs::write(&path, lurd::to_string(s.history()) + "\n") {
+                    Ok(()) => message.push_str(&format!("Solution is saved to {}.\r\n", path)),
+                    Err(err) => message.push_str(&warning_message(
+                        theme,
+                        &format!("Cannot save solution to {}: {}", path, err),
+                    )),
+                }
+                render(&s, theme, None, &header, PLAY_HELP, &message)?;
+                if level + 1 == collection.len() {
+                    break 'levels;
+                }
+                level += 1;
+                #[cfg(feature = "tui")]
+                {
+                    execute!(
+                        io::stdout(),
+                        Print("\r\n[Press any key to play next level].\r\n")
+                    )
+                    .map_err(|_err| Error::Terminal("Failed to Print."))?;
+                    read().map_err(|_err| Error::Terminal("Cannot read event."))?;
+                    //Wait for input.
+                }
+                continue 'levels;
+            }
+        }
     }
     Ok(())
 }
+
+/// Replays LURD `solution` on the level `args.level`.\
+/// (TUI) The replay can be paused, stepped, jumped and sped up by keys until Q is pressed.
+/// Without TUI, it shows every move.
+/// # Errors
+/// It returns `Err` when:
+/// * `solution` is not LURD
+/// * A move has failed, or the level is not solved
+/// * Rendering is failed
+fn replay(
+    args: &Arguments,
+    collection: &Collection,
+    theme: &Theme,
+    solution: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let level = args.level - 1;
+    let moves = lurd::parse(solution)?;
+    let header = level_header(collection, level, args.reverse);
+    let mut playback = Playback::new(&level_stage(args, collection, level)?, moves);
+    #[cfg(feature = "tui")]
+    view(&mut playback, theme, &header)?;
+    #[cfg(not(feature = "tui"))]
+    while !playback.is_finished() {
+        let status = format!("Replay: {}/{}\r\n", playback.position(), playback.len());
+        render(playback.stage(), theme, None, &header, "", &status)?;
+        playback.forward();
+    }
+    playback.jump(playback.len());
+    let help = if cfg!(feature = "tui") {
+        REPLAY_HELP
+    } else {
+        ""
+    };
+    render(
+        playback.stage(),
+        theme,
+        None,
+        &header,
+        help,
+        &replay_result(theme, &playback),
+    )?;
+    match playback.error() {
+        Some(err) => Err(err.clone().into()),
+        None => Ok(()),
+    }
+}
+
+/// Makes a message which describes whether the solution of `playback` solves the level.
+fn replay_result(theme: &Theme, playback: &Playback) -> String {
+    match playback.error() {
+        Some(err) => error_message(theme, err),
+        None => won_message(
+            theme,
+            &format!("Replay solved the level! {}", playback.stage().summary()),
+        ),
+    }
+}
+
+/// (TUI) Shows `playback` with `header` until Q is pressed.\
+/// Unless it is paused, the next move is played after the delay of the speed.
+/// # Errors
+/// It returns `Err(Error)` when rendering or reading input failed.
+#[cfg(feature = "tui")]
+fn view(playback: &mut Playback, theme: &Theme, header: &str) -> Result<(), Error> {
+    let mut speed = DEFAULT_SPEED;
+    let mut paused = false;
+    let mut message = String::new();
+    loop {
+        let mut status = format!(
+            "Replay: {}/{}, Speed: {}/{}{}\r\n",
+            playback.position(),
+            playback.len(),
+            speed + 1,
+            REPLAY_DELAYS.len(),
+            if paused { " (Paused)" } else { "" }
+        );
+        if playback.is_finished() {
+            status.push_str(&replay_result(theme, playback));
+        }
+        status.push_str(&message);
+        render(playback.stage(), theme, None, header, REPLAY_HELP, &status)?;
+        message.clear();
+        if !paused && !playback.is_finished() {
+            let delay = std::time::Duration::from_millis(REPLAY_DELAYS[speed]);
+            if !poll(delay).map_err(|_err| Error::InvalidInput)? {
+                playback.forward();
+                continue;
+            }
+        }
+        if let Event::Key(event) = read().map_err(|_err| Error::InvalidInput)? {
+            match event.code {
+                KeyCode::Char(' ') if playback.is_finished() => {
+                    playback.jump(0);
+                    paused = false;
+                }
+                KeyCode::Char(' ') => paused = !paused,
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('d') => {
+                    paused = true;
+                    playback.forward();
+                }
+                KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('a') => {
+                    paused = true;
+                    playback.back();
+                }
+                KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('w') | KeyCode::Char('+') => {
+                    speed = (speed + 1).min(REPLAY_DELAYS.len() - 1);
+                }
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('s') | KeyCode::Char('-') => {
+                    speed = speed.saturating_sub(1);
+                }
+                KeyCode::Home => playback.jump(0),
+                KeyCode::End => playback.jump(playback.len()),
+                KeyCode::Char('g') => {
+                    paused = true;
+                    let number = read_digits("Move: ")
+                        .and_then(|digits| digits.parse().map_err(|_err| Error::InvalidInput));
+                    match number {
+                        Ok(number) => playback.jump(number),
+                        Err(err) => message = error_message(theme, &err),
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                _ => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! LURD notation for pusher
+//!
+//! LURD is the standard notation of Sokoban solutions.
+//! `l`, `u`, `r`, `d` are moves and `L`, `U`, `R`, `D` are pushes.
+
+use super::error::Error;
+use super::stage::{Direction, Move, Stage};
+
+/// Converts a move to a LURD character.
+pub fn move_char(m: Move) -> char {
+    let c = match m.direction {
+        Direction::Left => 'l',
+        Direction::Up => 'u',
+        Direction::Right => 'r',
+        Direction::Down => 'd',
+    };
+    if m.pushed {
+        c.to_ascii_uppercase()
+    } else {
+        c
+    }
+}
+
+/// Converts a LURD character to a move, or `None` if it is not LURD.
+pub fn parse_char(c: char) -> Option<Move> {
+    let direction = match c.to_ascii_lowercase() {
+        'l' => Direction::Left,
+        'u' => Direction::Up,
+        'r' => Direction::Right,
+        'd' => Direction::Down,
+        _ => return None,
+    };
+    Some(Move {
+        direction,
+        pushed: c.is_ascii_uppercase(),
+    })
+}
+
+/// Converts moves to a LURD string.
+/// # Example
+/// ```
+/// use pusher::lurd;
+/// use pusher::{Direction, Stage};
+/// let mut stage = Stage::new("#@.O+#").unwrap();
+/// stage.move_player(Direction::Right).unwrap();
+/// stage.move_player(Direction::Right).unwrap();
+/// assert_eq!(lurd::to_string(stage.history()), "rR");
+/// ```
+pub fn to_string(moves: &[Move]) -> String {
+    moves.iter().map(|&m| move_char(m)).collect()
+}
+
+/// Parses a LURD string. Whitespaces are ignored.
+/// # Errors
+/// It returns `Err(Error::InvalidLurd)` when a character is not LURD.
+pub fn parse(string: &str) -> Result<Vec<Move>, Error> {
+    string
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .enumerate()
+        .map(|(index, c)| parse_char(c).ok_or(Error::InvalidLurd { index, ch: c }))
+        .collect()
+}
+
+/// Plays `moves` on `stage` by `Stage::move_player`, and checks the stage is solved.
+/// # Errors
+/// It returns `Err(Error)` when:
+/// * A move has failed.
+/// * A move pushes a ball but it is written as a move, or vice versa. (`Error::ReplayMismatch`)
+/// * The stage is not solved after all moves. (`Error::NotSolved`)
+pub fn replay(stage: &mut Stage, moves: &[Move]) -> Result<(), Error> {
+    for (index, &m) in moves.iter().enumerate() {
+        replay_move(stage, index, m)?;
+    }
+    if stage.is_won() {
+        Ok(())
+    } else {
+        Err(Error::NotSolved)
+    }
+}
+
+/// Plays a move of the replay, which is `index`-th move.
+/// # Errors
+/// Same as `replay` except `Error::NotSolved`.
+pub fn replay_move(stage: &mut Stage, index: usize, m: Move) -> Result<(), Error> {
+    stage.move_player(m.direction)?;
+    match stage.history().last() {
+        Some(played) if played.pushed == m.pushed => Ok(()),
+        _ => Err(Error::ReplayMismatch { index }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn round_trip() {
+        let moves = parse("lu rd\nLURD").unwrap();
+        assert_eq!(moves.len(), 8);
+        assert_eq!(to_string(&moves), "lurdLURD");
+        assert_eq!(parse("lx"), Err(Error::InvalidLurd { index: 1, ch: 'x' }));
+    }
+    #[test]
+    fn replay_solution() {
+        let stage = Stage::new("#@.O+#").unwrap();
+        assert_eq!(replay(&mut stage.clone(), &parse("rR").unwrap()), Ok(()));
+        assert_eq!(
+            replay(&mut stage.clone(), &parse("rr").unwrap()),
+            Err(Error::ReplayMismatch { index: 1 })
+        );
+        assert_eq!(
+            replay(&mut stage.clone(), &parse("r").unwrap()),
+            Err(Error::NotSolved)
+        );
+        assert_eq!(
+            replay(&mut stage.clone(), &parse("l").unwrap()),
+            Err(Error::BlockedByWall)
+        );
+    }
+}
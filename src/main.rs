@@ -1,25 +1,175 @@
 extern crate pusher;
 
 #[cfg(feature = "argparser")]
-use clap::App;
+use clap::{App, SubCommand};
 use std::process;
 /// Parses CLI arguments into struct Arguments
 #[cfg(not(feature = "argparser"))]
-fn parse_args(mut args: std::env::Args) -> pusher::Arguments {
+fn parse_args(mut args: impl Iterator<Item = String>) -> pusher::Arguments {
     let filename = args.next().unwrap_or_else(|| String::from("stage.data"));
-    pusher::Arguments { filename }
+    let level = args.next().and_then(|n| n.parse().ok()).unwrap_or(1);
+    pusher::Arguments {
+        filename,
+        format: None,
+        level,
+        replay: None,
+        moves: None,
+        theme: None,
+        reverse: false,
+    }
+}
+
+/// Parses stage files and the format of a subcommand. Exits on an unknown format.
+#[cfg(feature = "argparser")]
+fn file_args(matches: &clap::ArgMatches) -> (Vec<String>, Option<pusher::format::StageFormat>) {
+    let files = matches
+        .values_of("STAGE_FILE")
+        .map(|files| files.map(String::from).collect())
+        .unwrap_or_default();
+    let format = match matches.value_of("format").map(str::parse) {
+        Some(Ok(format)) => Some(format),
+        Some(Err(e)) => {
+            eprintln!("Application error: {}", e);
+            process::exit(1);
+        }
+        None => None,
+    };
+    (files, format)
+}
+
+/// Parses a number option of a subcommand. Exits if it is not a number.
+#[cfg(feature = "argparser")]
+fn number_arg<T: std::str::FromStr>(matches: &clap::ArgMatches, name: &str) -> Option<T> {
+    matches.value_of(name).map(|n| {
+        n.parse().unwrap_or_else(|_| {
+            eprintln!("Application error: {} has to be a number.", name);
+            process::exit(1);
+        })
+    })
+}
+
+/// Parses `--max-nodes` and `--time-limit` of a subcommand. Missing options are taken from `default`.
+#[cfg(feature = "argparser")]
+fn limit_args(matches: &clap::ArgMatches, default: pusher::astar::Limits) -> pusher::astar::Limits {
+    pusher::astar::Limits {
+        nodes: number_arg(matches, "max-nodes").or(default.nodes),
+        time: number_arg(matches, "time-limit")
+            .map(std::time::Duration::from_secs)
+            .or(default.time),
+    }
+}
+
+/// Returns the number of threads of this machine, which is the default of the solver.
+fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Makes a seed from the current time, which is used when no seed is given.
+fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Prints a generated stage to stdout, and the seed to stderr. Exits on failure.
+fn print_generated(options: pusher::generate::Options) {
+    match pusher::generate::generate(&options) {
+        Ok(stage) => {
+            eprintln!("Seed: {}", options.seed);
+            print!("{}", stage);
+        }
+        Err(e) => {
+            eprintln!("Application error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
 fn main() {
     #[cfg(not(feature = "argparser"))]
     {
-        let mut args = std::env::args();
+        let mut args = std::env::args().peekable();
         let binary_name = args.next().unwrap_or_else(|| String::from("pusher"));
+        if args.peek().map(String::as_str) == Some("validate") {
+            let files: Vec<String> = args.skip(1).collect();
+            if files.is_empty() {
+                eprintln!("Usage: {} validate <STAGE_FILE>...", binary_name);
+                process::exit(1);
+            }
+            process::exit(if pusher::validate_files(&files, None) {
+                0
+            } else {
+                1
+            });
+        }
+        if args.peek().map(String::as_str) == Some("rate") {
+            let files: Vec<String> = args.skip(1).collect();
+            if files.is_empty() {
+                eprintln!("Usage: {} rate <STAGE_FILE>...", binary_name);
+                process::exit(1);
+            }
+            process::exit(
+                if pusher::rate_files(&files, None, pusher::rating::DEFAULT_LIMITS) {
+                    0
+                } else {
+                    1
+                },
+            );
+        }
+        if args.peek().map(String::as_str) == Some("solve") {
+            let mut args = args.skip(1);
+            let filename = args.next();
+            let threads = args.next().map(|n| n.parse());
+            let (filename, threads) = match (filename, threads) {
+                (Some(filename), None) => (filename, default_threads()),
+                (Some(filename), Some(Ok(threads))) => (filename, threads),
+                _ => {
+                    eprintln!("Usage: {} solve <STAGE_FILE> [THREADS]", binary_name);
+                    process::exit(1);
+                }
+            };
+            let options = pusher::SolveOptions {
+                format: None,
+                level: None,
+                threads,
+                informed: false,
+                limits: Default::default(),
+            };
+            process::exit(if pusher::solve_file(&filename, &options) {
+                0
+            } else {
+                1
+            });
+        }
+        if args.peek().map(String::as_str) == Some("generate") {
+            let numbers: Result<Vec<u64>, _> = args.skip(1).map(|n| n.parse()).collect();
+            let numbers = match numbers {
+                Ok(numbers) if numbers.len() <= 4 => numbers,
+                _ => {
+                    eprintln!(
+                        "Usage: {} generate [SEED] [WIDTH] [HEIGHT] [BALLS]",
+                        binary_name
+                    );
+                    process::exit(1);
+                }
+            };
+            let default = pusher::generate::Options::default();
+            let get =
+                |index: usize, default: usize| numbers.get(index).map_or(default, |&n| n as usize);
+            print_generated(pusher::generate::Options {
+                seed: numbers.first().copied().unwrap_or_else(random_seed),
+                width: get(1, default.width),
+                height: get(2, default.height),
+                balls: get(3, default.balls),
+            });
+            process::exit(0);
+        }
 
         if let Err(e) = pusher::run(parse_args(args)) {
             eprintln!("Application error: {}", e);
             eprintln!(
-                "Usage: {} [STAGE_FILE]\nArgs:\n STAGE_FILE   default='stage.data'",
+                "Usage: {} [STAGE_FILE] [LEVEL]\nArgs:\n STAGE_FILE   default='stage.data'\n LEVEL        default=1",
                 binary_name
             );
             process::exit(1);
@@ -33,9 +183,106 @@ fn main() {
             .about("Simple clone of sokoban")
             .args_from_usage(
                 "--default-stage 'Shows default stage to stdout.'
+        --format [FORMAT] 'Sets the stage format: pusher or xsb. default=auto detect'
+        --level [LEVEL] 'Sets the level number to play first. default=1'
+        --moves [LURD] 'Replays the LURD moves of the level instead of a file.'
+        --replay [LURD_FILE] 'Replays the LURD solution of the level and verifies it.'
+        --reverse 'Plays in reverse mode, where the player pulls balls back to their start squares.'
+        --theme [THEME] 'Sets the theme: plain, color, emoji or a theme file. default=color with color feature, otherwise plain'
         [STAGE_FILE]     'Sets the stage file to play. default=\'stage.data\''",
             )
+            .subcommand(
+                SubCommand::with_name("validate")
+                    .about("Reports every problem of levels in the stage files.")
+                    .args_from_usage(
+                        "--format [FORMAT] 'Sets the stage format: pusher or xsb. default=auto detect'
+                <STAGE_FILE>...    'Stage files to validate'",
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("rate")
+                    .about("Prints a table of levels in the stage files sorted by difficulty.")
+                    .args_from_usage(
+                        "--format [FORMAT] 'Sets the stage format: pusher or xsb. default=auto detect'
+                --max-nodes [NODES] 'Leaves a level unrated after A* expands the number of states. default=200000'
+                --time-limit [SECONDS] 'Leaves a level unrated after the seconds.'
+                <STAGE_FILE>...    'Stage files to rate'",
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("solve")
+                    .about("Prints move-optimal solutions of levels in LURD.")
+                    .args_from_usage(
+                        "--format [FORMAT] 'Sets the stage format: pusher or xsb. default=auto detect'
+                --level [LEVEL] 'Solves only the level. default=every level'
+                --threads [THREADS] 'Sets the number of solver threads. default=number of CPUs'
+                --astar 'Finds push-optimal solutions by A* and prints statistics.'
+                --max-nodes [NODES] 'Stops A* after expanding the number of states.'
+                --time-limit [SECONDS] 'Stops A* after the seconds.'
+                <STAGE_FILE>    'Stage file to solve'",
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("generate")
+                    .about("Prints a random solvable stage.")
+                    .args_from_usage(
+                        "--seed [SEED] 'Sets the seed to make the same stage. default=current time'
+                --width [WIDTH] 'Sets the width including walls. default=8'
+                --height [HEIGHT] 'Sets the height including walls. default=8'
+                --balls [BALLS] 'Sets the number of balls. default=2'",
+                    ),
+            )
             .get_matches();
+        if let Some(matches) = matches.subcommand_matches("validate") {
+            let (files, format) = file_args(matches);
+            process::exit(if pusher::validate_files(&files, format) {
+                0
+            } else {
+                1
+            });
+        }
+        if let Some(matches) = matches.subcommand_matches("rate") {
+            let (files, format) = file_args(matches);
+            let limits = limit_args(matches, pusher::rating::DEFAULT_LIMITS);
+            process::exit(if pusher::rate_files(&files, format, limits) {
+                0
+            } else {
+                1
+            });
+        }
+        if let Some(matches) = matches.subcommand_matches("solve") {
+            let (files, format) = file_args(matches);
+            let options = pusher::SolveOptions {
+                format,
+                level: number_arg(matches, "level"),
+                threads: number_arg(matches, "threads").unwrap_or_else(default_threads),
+                informed: matches.is_present("astar"),
+                limits: limit_args(matches, pusher::astar::Limits::default()),
+            };
+            process::exit(if pusher::solve_file(&files[0], &options) {
+                0
+            } else {
+                1
+            });
+        }
+        if let Some(matches) = matches.subcommand_matches("generate") {
+            let default = pusher::generate::Options::default();
+            let number = |name: &str, default: u64| match matches.value_of(name).map(str::parse) {
+                Some(Ok(number)) => number,
+                Some(Err(_)) => {
+                    eprintln!("Application error: {} has to be a number.", name);
+                    process::exit(1);
+                }
+                None => default,
+            };
+            print_generated(pusher::generate::Options {
+                seed: number("seed", random_seed()),
+                width: number("width", default.width as u64) as usize,
+                height: number("height", default.height as u64) as usize,
+                balls: number("balls", default.balls as u64) as usize,
+            });
+            process::exit(0);
+        }
 
         let filename = String::from(matches.value_of("STAGE_FILE").unwrap_or("stage.data"));
         if matches.is_present("default-stage") {
@@ -48,7 +295,36 @@ fn main() {
             );
             process::exit(0);
         }
-        if let Err(e) = pusher::run(pusher::Arguments { filename }) {
+        let format = match matches.value_of("format").map(str::parse) {
+            Some(Ok(format)) => Some(format),
+            Some(Err(e)) => {
+                eprintln!("Application error: {}", e);
+                process::exit(1);
+            }
+            None => None,
+        };
+        let level = match matches.value_of("level").map(str::parse) {
+            Some(Ok(level)) => level,
+            Some(Err(_)) => {
+                eprintln!(
+                    "Application error: {}",
+                    pusher::error::Error::InvalidLevelNumber
+                );
+                process::exit(1);
+            }
+            None => 1,
+        };
+        let replay = matches.value_of("replay").map(String::from);
+        let theme = matches.value_of("theme").map(String::from);
+        if let Err(e) = pusher::run(pusher::Arguments {
+            filename,
+            format,
+            level,
+            replay,
+            moves: matches.value_of("moves").map(String::from),
+            theme,
+            reverse: matches.is_present("reverse"),
+        }) {
             eprintln!("Application error: {}", e);
             process::exit(1);
         }
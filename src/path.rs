@@ -0,0 +1,226 @@
+//! Pathfinding for pusher
+//!
+//! It finds the shortest way to walk the player to a tile,
+//! or to push a ball to a tile, without touching other balls.
+//! In reverse mode, the ball is pulled instead, and walks never pull a ball.
+
+use super::stage::{Direction, ObjectType, Stage, Tile};
+use super::vector2::Vector2;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+/// Finds the shortest walk of the player to `to` without pushing any ball.
+/// # Returns
+/// Directions to walk, or `None` if `to` cannot be reached.
+/// # Example
+/// ```
+/// use pusher::path::walk_path;
+/// use pusher::{Stage, Vector2};
+/// let stage = Stage::new("#####\n#@O.#\n#...#\n#+..#\n#####").unwrap();
+/// assert_eq!(
+///     walk_path(&stage, Vector2::new(3, 1)).map(|path| path.len()),
+///     Some(4)
+/// );
+/// assert_eq!(walk_path(&stage, Vector2::new(2, 1)), None);
+/// ```
+pub fn walk_path(stage: &Stage, to: Vector2) -> Option<Vec<Direction>> {
+    let start = stage.player_position();
+    if !is_free(stage, to, None) {
+        return None;
+    }
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert(key(start), None);
+    queue.push_back(start);
+    while let Some(pos) = queue.pop_front() {
+        if pos == to {
+            return Some(trace(&parents, key(pos)));
+        }
+        for &direction in Direction::ALL.iter() {
+            let next = pos + direction.as_vector2();
+            if is_free(stage, next, None)
+                && !(stage.is_reverse() && is_ball(stage, pos - direction.as_vector2()))
+                && !parents.contains_key(&key(next))
+            {
+                parents.insert(key(next), Some((key(pos), direction)));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Finds the shortest moves which push (or pull in reverse mode) the ball on `ball` to `to`.\
+/// Other balls are regarded as walls, and they are never pulled in reverse mode.
+/// # Returns
+/// Directions to walk and push, or `None` if there is no ball on `ball`
+/// or the ball cannot be pushed to `to`.
+/// # Example
+/// ```
+/// use pusher::path::push_path;
+/// use pusher::{Stage, Vector2};
+/// let stage = Stage::new("#####\n#@..#\n#.O.#\n#..+#\n#####").unwrap();
+/// let path = push_path(&stage, Vector2::new(2, 2), Vector2::new(3, 3)).unwrap();
+/// let mut played = stage.clone();
+/// path.iter().for_each(|&d| played.move_player(d).unwrap());
+/// assert!(played.is_won());
+/// ```
+pub fn push_path(stage: &Stage, ball: Vector2, to: Vector2) -> Option<Vec<Direction>> {
+    if !is_ball(stage, ball) || !is_free(stage, to, Some(ball)) {
+        return None;
+    }
+    let start = (ball, stage.player_position());
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert(state_key(start), None);
+    queue.push_back(start);
+    while let Some((current, player)) = queue.pop_front() {
+        if current == to {
+            return Some(trace(&parents, state_key((current, player))));
+        }
+        for &direction in Direction::ALL.iter() {
+            let next = player + direction.as_vector2();
+            let state = if stage.is_reverse() {
+                let behind = player - direction.as_vector2();
+                if next == current
+                    || !is_free(stage, next, Some(ball))
+                    || (behind != current && behind != ball && is_ball(stage, behind))
+                {
+                    continue;
+                }
+                if behind == current {
+                    (player, next)
+                } else {
+                    (current, next)
+                }
+            } else if next == current {
+                let beyond = next + direction.as_vector2();
+                if !is_free(stage, beyond, Some(ball)) {
+                    continue;
+                }
+                (beyond, next)
+            } else if is_free(stage, next, Some(ball)) {
+                (current, next)
+            } else {
+                continue;
+            };
+            if let Entry::Vacant(entry) = parents.entry(state_key(state)) {
+                entry.insert(Some((state_key((current, player)), direction)));
+                queue.push_back(state);
+            }
+        }
+    }
+    None
+}
+
+/// Checks `pos` is in the stage and is neither a wall nor a ball.\
+/// The ball on `moving` is not regarded as an obstacle.
+fn is_free(stage: &Stage, pos: Vector2, moving: Option<Vector2>) -> bool {
+    match stage.tile(pos).and_then(Tile::object) {
+        Some(ObjectType::Ball) => moving == Some(pos),
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// Checks a ball is on `pos`.
+fn is_ball(stage: &Stage, pos: Vector2) -> bool {
+    stage.tile(pos).and_then(Tile::object) == Some(ObjectType::Ball)
+}
+
+/// Makes a hashable key of the position.
+fn key(pos: Vector2) -> (i32, i32) {
+    (pos.get_x(), pos.get_y())
+}
+
+/// Makes a hashable key of positions of the ball and the player.
+fn state_key((ball, player): (Vector2, Vector2)) -> ((i32, i32), (i32, i32)) {
+    (key(ball), key(player))
+}
+
+/// Follows parents from `last` and returns directions from the start.
+fn trace<K: std::hash::Hash + Eq + Copy>(
+    parents: &HashMap<K, Option<(K, Direction)>>,
+    mut last: K,
+) -> Vec<Direction> {
+    let mut directions = Vec::new();
+    while let Some(&Some((parent, direction))) = parents.get(&last) {
+        directions.push(direction);
+        last = parent;
+    }
+    directions.reverse();
+    directions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn walk() {
+        let stage = Stage::new("######\n#@O..#\n#.##.#\n#....#\n#+####\n######").unwrap();
+        let path = walk_path(&stage, Vector2::new(3, 1)).unwrap();
+        assert_eq!(path.len(), 8);
+        let mut played = stage.clone();
+        path.iter().for_each(|&d| played.move_player(d).unwrap());
+        assert_eq!(played.player_position(), Vector2::new(3, 1));
+        assert_eq!(played.pushes(), 0);
+        assert_eq!(walk_path(&stage, stage.player_position()), Some(Vec::new()));
+        assert_eq!(walk_path(&stage, Vector2::new(0, 0)), None);
+        assert_eq!(walk_path(&stage, Vector2::new(9, 9)), None);
+    }
+    #[test]
+    fn push() {
+        let stage = Stage::new("######\n#@...#\n#.O..#\n#...+#\n######").unwrap();
+        let path = push_path(&stage, Vector2::new(2, 2), Vector2::new(4, 3)).unwrap();
+        let mut played = stage.clone();
+        path.iter().for_each(|&d| played.move_player(d).unwrap());
+        assert!(played.is_won());
+        assert_eq!(played.pushes(), 3);
+        assert_eq!(
+            push_path(&stage, Vector2::new(1, 2), Vector2::new(4, 3)),
+            None
+        );
+    }
+    #[test]
+    fn pull() {
+        let stage = Stage::new("######\n#@...#\n#.O..#\n#...+#\n######")
+            .unwrap()
+            .reversed();
+        let path = push_path(&stage, Vector2::new(4, 3), Vector2::new(2, 2)).unwrap();
+        let mut played = stage.clone();
+        path.iter().for_each(|&d| played.move_player(d).unwrap());
+        assert!(played.is_won());
+        let path = walk_path(&played, Vector2::new(4, 1)).unwrap();
+        path.iter().for_each(|&d| played.move_player(d).unwrap());
+        assert!(played.is_won());
+    }
+    #[test]
+    fn pull_beside_other_ball() {
+        // The other ball is behind the player, so moving right first would pull it.
+        let stage = Stage::new("#########\n#O......#\n#+@.O.+.#\n#.......#\n#########")
+            .unwrap()
+            .reversed();
+        let path = push_path(&stage, Vector2::new(6, 2), Vector2::new(4, 2)).unwrap();
+        let mut played = stage.clone();
+        path.iter().for_each(|&d| played.move_player(d).unwrap());
+        assert_eq!(played.pushes(), 2);
+        for &pos in &[Vector2::new(1, 2), Vector2::new(4, 2)] {
+            assert_eq!(
+                played.tile(pos).and_then(Tile::object),
+                Some(ObjectType::Ball)
+            );
+        }
+    }
+    #[test]
+    fn blocked_push() {
+        let stage = Stage::new("######\n#@O.O#\n#...+#\n#...+#\n######").unwrap();
+        assert_eq!(
+            push_path(&stage, Vector2::new(2, 1), Vector2::new(4, 1)),
+            None
+        );
+        assert_eq!(
+            push_path(&stage, Vector2::new(2, 1), Vector2::new(2, 3)),
+            None
+        );
+    }
+}
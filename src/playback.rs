@@ -0,0 +1,165 @@
+//! Playback of solutions for pusher
+//!
+//! A `Playback` checks moves on a stage once, and then moves back and forth
+//! between the start and the last valid move, like a video player.
+
+use super::error::Error;
+use super::lurd;
+use super::stage::{Move, Stage};
+
+/// Describes a solution being replayed on a stage.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    /// Stage after `position` moves
+    stage: Stage,
+    /// Moves which can be played from the start
+    moves: Vec<Move>,
+    /// Number of played moves
+    position: usize,
+    /// Error which stopped the solution, including `Error::NotSolved`
+    error: Option<Error>,
+}
+
+impl Playback {
+    /// Checks `moves` on current state of `stage` and starts at the beginning.\
+    /// Moves after a failed move are dropped, and the failure is kept in `error`.
+    /// # Example
+    /// ```
+    /// use pusher::error::Error;
+    /// use pusher::lurd;
+    /// use pusher::playback::Playback;
+    /// use pusher::Stage;
+    /// let stage = Stage::new("#@.O+#").unwrap();
+    /// let mut playback = Playback::new(&stage, lurd::parse("rRR").unwrap());
+    /// assert_eq!((playback.position(), playback.len()), (0, 2));
+    /// assert_eq!(playback.error(), Some(&Error::BlockedByBall));
+    /// playback.jump(5);
+    /// assert_eq!(playback.position(), 2);
+    /// assert!(playback.stage().is_won());
+    /// assert!(playback.back());
+    /// assert!(!playback.stage().is_won());
+    /// ```
+    pub fn new(stage: &Stage, mut moves: Vec<Move>) -> Playback {
+        let mut played = stage.clone();
+        let start = stage.moves();
+        let mut error = None;
+        for (index, &m) in moves.iter().enumerate() {
+            if let Err(err) = lurd::replay_move(&mut played, index, m) {
+                // A mismatched move is played, so it is taken back.
+                if played.moves() - start > index {
+                    let _ = played.undo();
+                }
+                error = Some(err);
+                break;
+            }
+        }
+        moves.truncate(played.moves() - start);
+        if error.is_none() && !played.is_won() {
+            error = Some(Error::NotSolved);
+        }
+        Playback {
+            stage: stage.clone(),
+            moves,
+            position: 0,
+            error,
+        }
+    }
+    /// Getter of the stage after played moves
+    pub fn stage(&self) -> &Stage {
+        &self.stage
+    }
+    /// Returns the number of played moves.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+    /// Returns the number of moves which can be played.
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+    /// Checks there is no move to play.
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+    /// Checks every move is played.
+    pub fn is_finished(&self) -> bool {
+        self.position == self.moves.len()
+    }
+    /// Returns the error which stopped the solution, or `None` if the solution solves the stage.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+    /// Plays the next move.
+    /// # Returns
+    /// `false` if every move is played already.
+    pub fn forward(&mut self) -> bool {
+        match self.moves.get(self.position) {
+            Some(m) => {
+                self.stage
+                    .move_player(m.direction)
+                    .expect("Checked move has failed.");
+                self.position += 1;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Takes back the last played move.
+    /// # Returns
+    /// `false` if no move is played.
+    pub fn back(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        self.stage.undo().expect("Played move cannot be undone.");
+        self.position -= 1;
+        true
+    }
+    /// Plays or takes back moves until `position` moves are played.
+    /// A position after the last move is the last move.
+    pub fn jump(&mut self, position: usize) {
+        let position = position.min(self.moves.len());
+        while self.position > position {
+            self.back();
+        }
+        while self.position < position {
+            self.forward();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn stepping() {
+        let stage = Stage::new("#######\n#@.O.+#\n#######").unwrap();
+        let mut playback = Playback::new(&stage, lurd::parse("rRR").unwrap());
+        assert_eq!((playback.len(), playback.error()), (3, None));
+        assert!(!playback.back());
+        assert!(playback.forward() && playback.forward());
+        assert_eq!(playback.stage().pushes(), 1);
+        playback.jump(3);
+        assert!(playback.is_finished() && playback.stage().is_won());
+        assert!(!playback.forward());
+        playback.jump(0);
+        assert_eq!(
+            playback
+                .stage()
+                .to_format_string(crate::format::StageFormat::Xsb),
+            stage.to_format_string(crate::format::StageFormat::Xsb)
+        );
+    }
+    #[test]
+    fn invalid() {
+        let stage = Stage::new("#######\n#@.O.+#\n#######").unwrap();
+        let playback = Playback::new(&stage, lurd::parse("rrR").unwrap());
+        assert_eq!(playback.len(), 1);
+        assert_eq!(playback.error(), Some(&Error::ReplayMismatch { index: 1 }));
+        let playback = Playback::new(&stage, lurd::parse("rR").unwrap());
+        assert_eq!(
+            (playback.len(), playback.error()),
+            (2, Some(&Error::NotSolved))
+        );
+        assert!(Playback::new(&stage, Vec::new()).is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+//! Difficulty rating for pusher
+//!
+//! It solves a stage by A* and combines metrics of the solution and the search into a score.
+//! A higher score means a harder stage.
+
+use super::astar::{self, Limits, Outcome};
+use super::error::Error;
+use super::stage::{ObjectType, Stage};
+
+/// Limits of A* which `pusher rate` uses by default
+pub const DEFAULT_LIMITS: Limits = Limits {
+    nodes: Some(200_000),
+    time: None,
+};
+
+/// Weight of a push of the optimal solution
+const PUSH_WEIGHT: f64 = 1.0;
+/// Weight of a ball
+const BALL_WEIGHT: f64 = 4.0;
+/// Weight of doubling searched states
+const SEARCH_WEIGHT: f64 = 3.0;
+/// Weight of the branching factor
+const BRANCHING_WEIGHT: f64 = 5.0;
+
+/// Describes metrics of a stage and its difficulty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    /// Pushes of the push-optimal solution
+    pub pushes: usize,
+    /// Moves of the push-optimal solution
+    pub moves: usize,
+    /// States which A* expanded
+    pub nodes: usize,
+    /// Number of balls
+    pub balls: usize,
+    /// Average number of possible pushes from a state
+    pub branching: f64,
+}
+
+impl Rating {
+    /// Combines the metrics into a difficulty score.\
+    /// `pushes + 4 * balls + 3 * log2(nodes + 1) + 5 * branching`
+    pub fn score(&self) -> f64 {
+        PUSH_WEIGHT * self.pushes as f64
+            + BALL_WEIGHT * self.balls as f64
+            + SEARCH_WEIGHT * (self.nodes as f64 + 1.0).log2()
+            + BRANCHING_WEIGHT * self.branching
+    }
+}
+
+/// Rates the difficulty of `stage` from its current state by A* within `limits`.
+/// # Returns
+/// Rating of the stage, or `None` if the stage cannot be solved.
+/// # Errors
+/// It returns `Err(Error::LimitReached)` if the search is stopped by `limits`,
+/// and `Err(Error::StageTooLarge)` if the stage has more than `bitboard::MAX_SQUARES` squares.
+/// # Example
+/// ```
+/// use pusher::rating::{rate, DEFAULT_LIMITS};
+/// use pusher::Stage;
+/// let easy = rate(&Stage::new("#@O+#").unwrap(), DEFAULT_LIMITS).unwrap().unwrap();
+/// let hard = Stage::new("#####\n#@..#\n#.OO#\n#+.+#\n#####").unwrap();
+/// let hard = rate(&hard, DEFAULT_LIMITS).unwrap().unwrap();
+/// assert_eq!((easy.pushes, easy.balls), (1, 1));
+/// assert!(easy.score() < hard.score());
+/// ```
+pub fn rate(stage: &Stage, limits: Limits) -> Result<Option<Rating>, Error> {
+    let (outcome, statistics) = astar::solve(stage, limits)?;
+    let solution = match outcome {
+        Outcome::Solved(solution) => solution,
+        Outcome::Unsolvable => return Ok(None),
+        Outcome::LimitReached => return Err(Error::LimitReached),
+    };
+    let mut played = stage.snapshot();
+    let mut pushes = 0;
+    for &direction in &solution {
+        pushes += played.step(direction)? as usize;
+    }
+    Ok(Some(Rating {
+        pushes,
+        moves: solution.len(),
+        nodes: statistics.expanded,
+        balls: stage
+            .tiles()
+            .filter(|(_, tile)| tile.object() == Some(ObjectType::Ball))
+            .count(),
+        branching: statistics.branching_factor(),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn metrics() {
+        let stage = Stage::new("#######\n#@....#\n#.O...#\n#...+.#\n#######").unwrap();
+        let rating = rate(&stage, DEFAULT_LIMITS).unwrap().unwrap();
+        assert_eq!(rating.pushes, 3);
+        assert_eq!(rating.balls, 1);
+        assert!(rating.nodes > 0);
+        assert!(rating.branching > 0.0);
+        assert_eq!(
+            rate(
+                &Stage::new("#####\n#O.@#\n#..+#\n#####").unwrap(),
+                DEFAULT_LIMITS
+            ),
+            Ok(None)
+        );
+        let won = rate(&Stage::new("#@#").unwrap(), DEFAULT_LIMITS)
+            .unwrap()
+            .unwrap();
+        assert_eq!((won.pushes, won.nodes), (0, 0));
+        let limits = Limits {
+            nodes: Some(1),
+            time: None,
+        };
+        assert_eq!(rate(&stage, limits), Err(Error::LimitReached));
+    }
+}
@@ -0,0 +1,220 @@
+//! Saved game for pusher
+//!
+//! A saved game records the level number, moves in LURD and the stage at the moment.
+//! `Mode: reverse` line is written only for a game in reverse mode.
+//! ```text
+//! Level: 1
+//! Time: 42
+//! Moves: rrU
+//! Undone: l
+//! Stage:
+//! #####
+//! #.@*#
+//! #####
+//! ```
+
+use super::collection::Collection;
+use super::error::Error;
+use super::format::StageFormat;
+use super::lurd;
+use super::stage::{Move, Stage};
+use std::fmt;
+use std::time::Duration;
+
+/// Describes a saved game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveGame {
+    /// Number of the level (1-based)
+    pub level: usize,
+    /// Whether the game is in reverse mode
+    pub reverse: bool,
+    /// Elapsed time of the level
+    pub elapsed: Duration,
+    /// Moves from the beginning of the level
+    pub moves: Vec<Move>,
+    /// Undone moves which can be redone
+    pub undone: Vec<Move>,
+    /// Stage at the moment in XSB format
+    pub stage: String,
+}
+
+impl SaveGame {
+    /// Captures the stage of the level (1-based).
+    /// # Errors
+    /// It returns `Err` propagated from `Stage::to_format_string`.
+    pub fn new(level: usize, stage: &Stage) -> Result<SaveGame, Error> {
+        Ok(SaveGame {
+            level,
+            reverse: stage.is_reverse(),
+            elapsed: Duration::from_secs(stage.elapsed().as_secs()),
+            moves: stage.history().to_vec(),
+            undone: stage.undone().to_vec(),
+            stage: stage.to_format_string(StageFormat::Xsb)?,
+        })
+    }
+    /// Parses a saved game.
+    /// # Errors
+    /// It returns `Err(Error::InvalidSave)` when a field is missing or invalid.
+    pub fn parse(string: &str) -> Result<SaveGame, Error> {
+        let mut level = None;
+        let mut reverse = false;
+        let mut elapsed = Duration::from_secs(0);
+        let mut moves = Vec::new();
+        let mut undone = Vec::new();
+        let mut lines = string.lines();
+        let mut stage = None;
+        while let Some(line) = lines.next() {
+            let (key, value) = match line.find(':') {
+                Some(i) => (&line[..i], line[i + 1..].trim()),
+                None => return Err(Error::InvalidSave),
+            };
+            match key {
+                "Level" => level = Some(value.parse().map_err(|_err| Error::InvalidSave)?),
+                "Mode" => {
+                    reverse = match value {
+                        "reverse" => true,
+                        "normal" => false,
+                        _ => return Err(Error::InvalidSave),
+                    }
+                }
+                "Time" => {
+                    elapsed = Duration::from_secs(value.parse().map_err(|_err| Error::InvalidSave)?)
+                }
+                "Moves" => moves = lurd::parse(value)?,
+                "Undone" => undone = lurd::parse(value)?,
+                "Stage" => {
+                    stage = Some(lines.by_ref().fold(String::new(), |mut s, l| {
+                        s.push_str(l);
+                        s.push('\n');
+                        s
+                    }))
+                }
+                _ => return Err(Error::InvalidSave),
+            }
+        }
+        match (level, stage) {
+            (Some(level), Some(stage)) => Ok(SaveGame {
+                level,
+                reverse,
+                elapsed,
+                moves,
+                undone,
+                stage,
+            }),
+            _ => Err(Error::InvalidSave),
+        }
+    }
+    /// Restores the stage from the level of `collection`, which is reversed in reverse mode.\
+    /// It replays moves and undone moves, then undoes the undone moves.
+    /// # Errors
+    /// It returns `Err(Error)` when:
+    /// * The level is not in the collection. (`Error::LevelOutOfRange`)
+    /// * A move has failed.
+    /// * Restored stage differs from the saved stage. (`Error::InvalidSave`)
+    pub fn restore(
+        &self,
+        collection: &Collection,
+        format: Option<StageFormat>,
+    ) -> Result<Stage, Error> {
+        if self.level == 0 || self.level > collection.len() {
+            return Err(Error::LevelOutOfRange {
+                level: self.level,
+                count: collection.len(),
+            });
+        }
+        let mut stage = collection.levels()[self.level - 1].stage(format)?;
+        if self.reverse {
+            stage = stage.reversed();
+        }
+        let redo: Vec<Move> = self.undone.iter().rev().copied().collect();
+        for (index, &m) in self.moves.iter().chain(redo.iter()).enumerate() {
+            lurd::replay_move(&mut stage, index, m)?;
+        }
+        for _ in &self.undone {
+            stage.undo()?;
+        }
+        if stage.to_format_string(StageFormat::Xsb)? != self.stage {
+            return Err(Error::InvalidSave);
+        }
+        stage.resume_timer(self.elapsed);
+        Ok(stage)
+    }
+}
+
+impl fmt::Display for SaveGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Level: {}", self.level)?;
+        if self.reverse {
+            writeln!(f, "Mode: reverse")?;
+        }
+        write!(
+            f,
+            "Time: {}\nMoves: {}\nUndone: {}\nStage:\n{}",
+            self.elapsed.as_secs(),
+            lurd::to_string(&self.moves),
+            lurd::to_string(&self.undone),
+            self.stage
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stage::Direction;
+    #[test]
+    fn round_trip() {
+        let collection = Collection::parse("#@O+#\n\n#@.O+.#\n").unwrap();
+        let mut stage = collection.levels()[1].stage(None).unwrap();
+        stage.move_player(Direction::Right).unwrap();
+        stage.move_player(Direction::Right).unwrap();
+        stage.move_player(Direction::Right).unwrap();
+        stage.undo().unwrap();
+        stage.undo().unwrap();
+        let save = SaveGame::new(2, &stage).unwrap();
+        let parsed = SaveGame::parse(&save.to_string()).unwrap();
+        assert_eq!(parsed, save);
+        let mut restored = parsed.restore(&collection, None).unwrap();
+        assert_eq!(restored.history(), stage.history());
+        assert_eq!(restored.undone(), stage.undone());
+        restored.redo().unwrap();
+        assert!(restored.is_won());
+    }
+    #[test]
+    fn reverse() {
+        let collection = Collection::parse("#.O+@.#\n").unwrap();
+        let mut stage = collection.levels()[0].stage(None).unwrap().reversed();
+        stage.move_player(Direction::Right).unwrap();
+        let save = SaveGame::new(1, &stage).unwrap();
+        assert!(save.to_string().starts_with("Level: 1\nMode: reverse\n"));
+        let restored = SaveGame::parse(&save.to_string())
+            .unwrap()
+            .restore(&collection, None)
+            .unwrap();
+        assert!(restored.is_reverse());
+        assert_eq!(restored.history(), stage.history());
+        assert_eq!(
+            SaveGame::parse("Level: 1\nMode: upside\nStage:\n"),
+            Err(Error::InvalidSave)
+        );
+    }
+    #[test]
+    fn invalid() {
+        let collection = Collection::parse("#@O+#\n").unwrap();
+        assert_eq!(
+            SaveGame::parse("Level: x\nStage:\n"),
+            Err(Error::InvalidSave)
+        );
+        assert_eq!(SaveGame::parse("Moves: r\n"), Err(Error::InvalidSave));
+        let save = SaveGame::parse("Level: 1\nMoves: r\nStage:\n#@O+#\n").unwrap();
+        assert_eq!(
+            save.restore(&collection, None).unwrap_err(),
+            Error::ReplayMismatch { index: 0 }
+        );
+        let save = SaveGame::parse("Level: 1\nMoves: R\nStage:\n#@O+#\n").unwrap();
+        assert_eq!(
+            save.restore(&collection, None).unwrap_err(),
+            Error::InvalidSave
+        );
+    }
+}
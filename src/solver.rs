@@ -0,0 +1,465 @@
+//! Solver for pusher
+//!
+//! It searches the shortest solution of a stage.
+//! The solver moves player by the same rule as `Stage::move_player`,
+//! skips deadlocked states, and detects repeated states by Zobrist keys.
+
+use super::astar::{self, Limits, Outcome};
+use super::bitboard::{Bitset, Board, State, DEFAULT_WORDS};
+use super::error::Error;
+use super::stage::{Direction, Stage};
+use super::vector2::Vector2;
+use super::zobrist::TranspositionTable;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of locks of the shared visited set of the parallel solver
+const SHARDS: usize = 64;
+
+/// Describes what the solution minimizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveMode {
+    /// Minimizes the number of moves.
+    Moves,
+    /// Minimizes the number of pushes, then the number of moves.
+    Pushes,
+}
+
+/// Describes the next push of a solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hint {
+    /// Position of the ball to push
+    pub ball: Vector2,
+    /// Direction to push the ball
+    pub direction: Direction,
+}
+
+/// Describes how much the solver searched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// Number of states whose moves are tried
+    pub expanded: usize,
+    /// Number of moves from the expanded states which are possible and not deadlocked
+    pub generated: usize,
+    /// Time of the search
+    pub elapsed: Duration,
+}
+
+impl Statistics {
+    /// Returns the average number of possible moves from a state.
+    pub fn branching_factor(&self) -> f64 {
+        if self.expanded == 0 {
+            0.0
+        } else {
+            self.generated as f64 / self.expanded as f64
+        }
+    }
+}
+
+/// Describes a searched state, which links to its parent.
+struct Node {
+    /// Index of the parent node
+    parent: usize,
+    /// Direction from the parent node
+    direction: Direction,
+}
+
+/// Finds an optimal solution from current state of `stage`.
+/// # Returns
+/// Directions to solve the stage, or `None` if the stage cannot be solved.
+/// # Example
+/// ```
+/// use pusher::solver::{solve, SolveMode};
+/// use pusher::{Direction, Stage};
+/// let stage = Stage::new("#@.O+#").unwrap();
+/// assert_eq!(
+///     solve(&stage, SolveMode::Moves),
+///     Some(vec![Direction::Right, Direction::Right])
+/// );
+/// ```
+pub fn solve(stage: &Stage, mode: SolveMode) -> Option<Vec<Direction>> {
+    solve_with_statistics(stage, mode).0
+}
+
+/// Same as `solve`, but it also returns how much the solver searched.
+pub fn solve_with_statistics(
+    stage: &Stage,
+    mode: SolveMode,
+) -> (Option<Vec<Direction>>, Statistics) {
+    let mut statistics = Statistics::default();
+    if stage.is_won() {
+        return (Some(Vec::new()), statistics);
+    }
+    if stage.is_deadlocked() {
+        return (None, statistics);
+    }
+    let started = Instant::now();
+    let solution = match mode {
+        SolveMode::Moves => solve_moves(stage, &mut statistics),
+        SolveMode::Pushes => solve_pushes(stage, &mut statistics),
+    };
+    statistics.elapsed = started.elapsed();
+    (solution, statistics)
+}
+
+/// Finds a move-optimal solution like `solve(stage, SolveMode::Moves)` with `threads` worker threads.\
+/// Each depth of the search is split among the threads, which share a visited set.
+/// When several states reach the same position, the one earlier in the frontier wins,
+/// so the solution is the same as the single-threaded one regardless of thread scheduling.
+/// States are `bitboard::State`s, and a stage larger than `bitboard::MAX_SQUARES` is solved by `solve`.
+/// # Example
+/// ```
+/// use pusher::solver::{solve, solve_parallel, SolveMode};
+/// use pusher::Stage;
+/// let stage = Stage::new("#####\n#@..#\n#.O.#\n#..+#\n#####").unwrap();
+/// assert_eq!(solve_parallel(&stage, 4), solve(&stage, SolveMode::Moves));
+/// ```
+pub fn solve_parallel(stage: &Stage, threads: usize) -> Option<Vec<Direction>> {
+    if stage.is_won() {
+        return Some(Vec::new());
+    }
+    if stage.is_deadlocked() {
+        return None;
+    }
+    match stage.width() * stage.height() {
+        squares if squares <= Bitset::<DEFAULT_WORDS>::CAPACITY => {
+            search_parallel::<DEFAULT_WORDS>(stage, threads)
+        }
+        squares if squares <= Bitset::<64>::CAPACITY => search_parallel::<64>(stage, threads),
+        squares if squares <= Bitset::<256>::CAPACITY => search_parallel::<256>(stage, threads),
+        _ => solve(stage, SolveMode::Moves),
+    }
+}
+
+/// Runs `solve_parallel` over bitboards of `W` words.
+fn search_parallel<const W: usize>(stage: &Stage, threads: usize) -> Option<Vec<Direction>> {
+    let (board, root) = Board::<W>::from_stage(stage).ok()?;
+    // Key of a state -> (depth, order in the depth) of the first state which reached it
+    let visited: Vec<Mutex<HashMap<u64, (usize, usize)>>> =
+        (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect();
+    visited[root.zobrist_key() as usize % SHARDS]
+        .lock()
+        .unwrap()
+        .insert(root.zobrist_key(), (0, 0));
+    let mut nodes = vec![root_node()];
+    let mut frontier = vec![(0, root)];
+    let threads = threads.max(1);
+    for depth in 1.. {
+        if frontier.is_empty() {
+            return None;
+        }
+        let chunk = (frontier.len() + threads - 1) / threads;
+        let mut found: Vec<(usize, u64, State<W>)> = thread::scope(|scope| {
+            let workers: Vec<_> = frontier
+                .chunks(chunk)
+                .enumerate()
+                .map(|(i, states)| {
+                    let (board, visited) = (&board, &visited);
+                    scope.spawn(move || expand(board, states, i * chunk, depth, visited))
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        found.retain(|(order, key, _)| {
+            visited[*key as usize % SHARDS].lock().unwrap()[key] == (depth, *order)
+        });
+        found.sort_by_key(|(order, _, _)| *order);
+        let mut next = Vec::with_capacity(found.len());
+        for (order, _, state) in found {
+            nodes.push(Node {
+                parent: frontier[order / Direction::ALL.len()].0,
+                direction: Direction::ALL[order % Direction::ALL.len()],
+            });
+            if board.is_won(&state) {
+                return Some(trace(&nodes, nodes.len() - 1));
+            }
+            next.push((nodes.len() - 1, state));
+        }
+        frontier = next;
+    }
+    None
+}
+
+/// Tries every move from `states` of the frontier, which starts at `offset`.\
+/// A new state is claimed in `visited` by its order, which is `4 * (index in frontier) + direction`.
+/// # Returns
+/// Order, Zobrist key and state of the states which this worker has claimed.
+/// A claim can be taken by an earlier order of another worker later.
+fn expand<const W: usize>(
+    board: &Board<W>,
+    states: &[(usize, State<W>)],
+    offset: usize,
+    depth: usize,
+    visited: &[Mutex<HashMap<u64, (usize, usize)>>],
+) -> Vec<(usize, u64, State<W>)> {
+    let mut found = Vec::new();
+    for (i, (_, current)) in states.iter().enumerate() {
+        for (d, &direction) in Direction::ALL.iter().enumerate() {
+            let mut next = *current;
+            // A pushed ball is ahead of the player. Reverse mode has no dead squares.
+            let dead = match board.step(&mut next, direction) {
+                Ok(pushed) => {
+                    pushed
+                        && board
+                            .neighbor(next.player, direction)
+                            .map_or(false, |ball| board.is_dead_square(ball))
+                }
+                Err(_) => true,
+            };
+            if dead {
+                continue;
+            }
+            let key = next.zobrist_key();
+            let order = (offset + i) * Direction::ALL.len() + d;
+            let mut shard = visited[key as usize % SHARDS].lock().unwrap();
+            match shard.get(&key) {
+                Some(&claim) if claim <= (depth, order) => continue,
+                _ => {
+                    shard.insert(key, (depth, order));
+                }
+            }
+            drop(shard);
+            found.push((order, key, next));
+        }
+    }
+    found
+}
+
+/// Finds the first push of a push-optimal solution from current state of `stage` by A* within `limits`.\
+/// In reverse mode, the push is a pull of the ball behind the player.
+/// # Returns
+/// The next push, or `None` if the stage is already won or cannot be solved.
+/// # Errors
+/// It returns `Err(Error::LimitReached)` if the search is stopped by `limits`,
+/// and `Err(Error::StageTooLarge)` if the stage has more than `bitboard::MAX_SQUARES` squares.
+/// # Example
+/// ```
+/// use pusher::astar::Limits;
+/// use pusher::solver::{hint, Hint};
+/// use pusher::{Direction, Stage, Vector2};
+/// let stage = Stage::new("#@.O+#").unwrap();
+/// assert_eq!(
+///     hint(&stage, Limits::default()),
+///     Ok(Some(Hint {
+///         ball: Vector2::new(3, 0),
+///         direction: Direction::Right
+///     }))
+/// );
+/// ```
+pub fn hint(stage: &Stage, limits: Limits) -> Result<Option<Hint>, Error> {
+    let solution = match astar::solve(stage, limits)?.0 {
+        Outcome::Solved(solution) => solution,
+        Outcome::Unsolvable => return Ok(None),
+        Outcome::LimitReached => return Err(Error::LimitReached),
+    };
+    let mut current = stage.snapshot();
+    for direction in solution {
+        let ball = if current.is_reverse() {
+            current.player_position() - direction.as_vector2()
+        } else {
+            current.player_position() + direction.as_vector2()
+        };
+        if current.step(direction)? {
+            return Ok(Some(Hint { ball, direction }));
+        }
+    }
+    Ok(None)
+}
+
+/// Breadth-first search over single moves.
+fn solve_moves(stage: &Stage, statistics: &mut Statistics) -> Option<Vec<Direction>> {
+    let root = stage.snapshot();
+    let mut nodes = vec![root_node()];
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert(root.zobrist_key());
+    queue.push_back((0, root));
+    while let Some((index, current)) = queue.pop_front() {
+        statistics.expanded += 1;
+        for &direction in Direction::ALL.iter() {
+            let mut next = current.clone();
+            if next.step(direction).is_err() || next.is_deadlocked() {
+                continue;
+            }
+            statistics.generated += 1;
+            if !visited.insert(next.zobrist_key()) {
+                continue;
+            }
+            nodes.push(Node {
+                parent: index,
+                direction,
+            });
+            if next.is_won() {
+                return Some(trace(&nodes, nodes.len() - 1));
+            }
+            queue.push_back((nodes.len() - 1, next));
+        }
+    }
+    None
+}
+
+/// Uniform-cost search whose cost is (pushes, moves).
+fn solve_pushes(stage: &Stage, statistics: &mut Statistics) -> Option<Vec<Direction>> {
+    let root = stage.snapshot();
+    let mut nodes = vec![root_node()];
+    let mut best = TranspositionTable::new();
+    let mut closed = HashSet::new();
+    let mut heap = BinaryHeap::new();
+    best.insert(root.zobrist_key(), (0, 0));
+    let mut stages = vec![Some(root)];
+    heap.push(Reverse((0u32, 0u32, 0usize)));
+    while let Some(Reverse((pushes, moves, index))) = heap.pop() {
+        let current = match stages[index].take() {
+            Some(current) => current,
+            None => continue,
+        };
+        if !closed.insert(current.zobrist_key()) {
+            continue;
+        }
+        if current.is_won() {
+            return Some(trace(&nodes, index));
+        }
+        statistics.expanded += 1;
+        for &direction in Direction::ALL.iter() {
+            let mut next = current.clone();
+            let cost = match next.step(direction) {
+                Ok(true) => (pushes + 1, moves + 1),
+                Ok(false) => (pushes, moves + 1),
+                Err(_) => continue,
+            };
+            if next.is_deadlocked() {
+                continue;
+            }
+            statistics.generated += 1;
+            let key = next.zobrist_key();
+            if closed.contains(&key) || !best.insert_if(key, cost, |new, old| new < old) {
+                continue;
+            }
+            nodes.push(Node {
+                parent: index,
+                direction,
+            });
+            stages.push(Some(next));
+            heap.push(Reverse((cost.0, cost.1, nodes.len() - 1)));
+        }
+    }
+    None
+}
+
+/// Makes the root node, which has no parent.
+fn root_node() -> Node {
+    Node {
+        parent: usize::MAX,
+        direction: Direction::Up,
+    }
+}
+
+/// Follows parents from `index` and returns directions from the root.
+fn trace(nodes: &[Node], mut index: usize) -> Vec<Direction> {
+    let mut directions = Vec::new();
+    while nodes[index].parent != usize::MAX {
+        directions.push(nodes[index].direction);
+        index = nodes[index].parent;
+    }
+    directions.reverse();
+    directions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stage::{ObjectType, Tile};
+    /// Applies directions and checks the stage is solved.
+    fn check(stage: &Stage, directions: &[Direction]) -> bool {
+        let mut s = stage.clone();
+        directions.iter().all(|&d| s.move_player(d).is_ok()) && s.is_won()
+    }
+    #[test]
+    fn moves_optimal() {
+        let stage = Stage::new("#####\n#@..#\n#.O.#\n#..+#\n#####").unwrap();
+        let solution = solve(&stage, SolveMode::Moves).unwrap();
+        assert!(check(&stage, &solution));
+        assert_eq!(solution.len(), 5);
+    }
+    #[test]
+    fn pushes_optimal() {
+        let stage = Stage::new("#######\n#@....#\n#.O...#\n#...+.#\n#######").unwrap();
+        let solution = solve(&stage, SolveMode::Pushes).unwrap();
+        assert!(check(&stage, &solution));
+        let pushes = {
+            let mut s = stage.clone();
+            solution.iter().for_each(|&d| s.move_player(d).unwrap());
+            s.history().iter().filter(|m| m.pushed).count()
+        };
+        assert_eq!(pushes, 3);
+    }
+    #[test]
+    fn unsolvable() {
+        let stage = Stage::new("#####\n#O.@#\n#..+#\n#####").unwrap();
+        assert_eq!(solve(&stage, SolveMode::Moves), None);
+        assert_eq!(solve(&stage, SolveMode::Pushes), None);
+    }
+    #[test]
+    fn hint_next_push() {
+        let stage = Stage::new("#######\n#@....#\n#.O...#\n#...+.#\n#######").unwrap();
+        let first = hint(&stage, Limits::default()).unwrap().unwrap();
+        let mut s = stage.clone();
+        s.move_player(Direction::Down).unwrap();
+        assert_eq!(first.ball, Vector2::new(2, 2));
+        assert_eq!(
+            hint(&s, Limits::default()).map(|h| h.map(|h| h.ball)),
+            Ok(Some(Vector2::new(2, 2)))
+        );
+        let stuck = Stage::new("#####\n#O.@#\n#..+#\n#####").unwrap();
+        assert_eq!(hint(&stuck, Limits::default()), Ok(None));
+        assert_eq!(
+            hint(&Stage::new("#@#").unwrap(), Limits::default()),
+            Ok(None)
+        );
+        let limits = Limits {
+            nodes: Some(0),
+            time: None,
+        };
+        assert_eq!(hint(&stage, limits), Err(Error::LimitReached));
+        let reverse = stage.reversed();
+        let pull = hint(&reverse, Limits::default()).unwrap().unwrap();
+        assert_eq!(
+            reverse.tile(pull.ball).and_then(Tile::object),
+            Some(ObjectType::Ball)
+        );
+    }
+    #[test]
+    fn parallel_deterministic() {
+        let stage = Stage::new("#######\n#@....#\n#.O.O.#\n#..+.+#\n#######").unwrap();
+        let expected = solve(&stage, SolveMode::Moves);
+        assert!(check(&stage, expected.as_ref().unwrap()));
+        for threads in 1..6 {
+            assert_eq!(solve_parallel(&stage, threads), expected);
+        }
+        let reverse = stage.reversed();
+        assert_eq!(
+            solve_parallel(&reverse, 3),
+            solve(&reverse, SolveMode::Moves)
+        );
+        let long = Stage::new(&format!("#@O{}+#", ".".repeat(1100))).unwrap();
+        assert_eq!(solve_parallel(&long, 2), Some(vec![Direction::Right; 1101]));
+        let stuck = Stage::new("#####\n#O.@#\n#..+#\n#####").unwrap();
+        assert_eq!(solve_parallel(&stuck, 3), None);
+        assert_eq!(
+            solve_parallel(&Stage::new("#@#").unwrap(), 0),
+            Some(Vec::new())
+        );
+    }
+    #[test]
+    fn already_won() {
+        let stage = Stage::new("#@#").unwrap();
+        assert_eq!(solve(&stage, SolveMode::Moves), Some(Vec::new()));
+        assert_eq!(solve(&stage, SolveMode::Pushes), Some(Vec::new()));
+    }
+}
@@ -2,26 +2,33 @@
 //!
 //! It contains Stage structure of pusher.
 
+use super::deadlock;
+use super::error::Error;
+use super::format::StageFormat;
+use super::theme::Theme;
 use super::vector2::Vector2;
+use super::viewport::Viewport;
+use super::zobrist;
 use std::convert::TryInto;
 use std::fmt;
 use std::ops;
+use std::time::{Duration, Instant};
 /// A character that represents Wall
-const WALL_CHR: char = '#';
+pub(crate) const WALL_CHR: char = '#';
 /// A character that represents Emptiness
-const EMPTY_CHR: char = '.';
+pub(crate) const EMPTY_CHR: char = '.';
 /// A character that represents Ball
-const BALL_CHR: char = 'O';
+pub(crate) const BALL_CHR: char = 'O';
 /// A character that represents Goal
-const GOAL_CHR: char = '+';
+pub(crate) const GOAL_CHR: char = '+';
 /// A character that represents Ball on the Goal,
-/// Only appears in Display trait
-const BALL_ON_GOAL_CHR: char = '$';
+/// Only appears in the plain theme
+pub(crate) const BALL_ON_GOAL_CHR: char = '$';
 /// A character that represents Player
-const PLAYER_CHR: char = '@';
+pub(crate) const PLAYER_CHR: char = '@';
 
 /// Describes type of Object: Empty, Ball, Player
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ObjectType {
     Empty,
     Ball,
@@ -29,65 +36,145 @@ pub enum ObjectType {
 }
 
 impl fmt::Display for ObjectType {
-    #[cfg(not(feature = "color"))]
+    /// Writes the object on a plain in the default theme.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let charactor = match self {
-            ObjectType::Empty => EMPTY_CHR,
-            ObjectType::Ball => BALL_CHR,
-            ObjectType::Player => PLAYER_CHR,
-        };
-        write!(f, "{}", charactor)
-    }
-    #[cfg(feature = "color")]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let styled_charactor = match self {
-            ObjectType::Empty => ("\x1b[1;30m", EMPTY_CHR),
-            ObjectType::Ball => ("\x1b[0;37m", BALL_CHR),
-            ObjectType::Player => ("\x1b[0;32m", PLAYER_CHR),
-        };
-        write!(f, "{}{}", styled_charactor.0, styled_charactor.1)
+        write!(f, "{}", Theme::default().object_style(*self))
     }
 }
-/// Describes type of Tile: Wall, Plain, Goal\
+/// Describes type of Tile: Wall, Plain, Goal, Outside\
 /// Plain and Goal can hold an Object.
-#[derive(Debug, Clone)]
+/// Outside is the space out of walls, where nothing can enter.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Tile {
     Wall,
     Plain(ObjectType),
     Goal(ObjectType),
+    Outside,
 }
 
-impl fmt::Display for Tile {
-    #[cfg(not(feature = "color"))]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Tile {
+    /// Returns the object on the tile, or `None` for a wall and outside.
+    pub fn object(&self) -> Option<ObjectType> {
         match self {
-            Tile::Wall => write!(f, "{}", WALL_CHR),
-            Tile::Plain(obj) => write!(f, "{}", obj),
-            Tile::Goal(ObjectType::Empty) => write!(f, "{}", GOAL_CHR),
-            Tile::Goal(ObjectType::Ball) => write!(f, "{}", BALL_ON_GOAL_CHR),
-            Tile::Goal(obj) => write!(f, "{}", obj),
+            Tile::Wall | Tile::Outside => None,
+            Tile::Plain(obj) | Tile::Goal(obj) => Some(*obj),
         }
     }
-    #[cfg(feature = "color")]
+    /// Checks the tile is a goal.
+    pub fn is_goal(&self) -> bool {
+        matches!(self, Tile::Goal(_))
+    }
+}
+
+impl fmt::Display for Tile {
+    /// Writes the tile in the default theme.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Tile::Wall => write!(f, "\x1b[1;31m{}", WALL_CHR),
-            Tile::Plain(obj) => write!(f, "{}", obj),
-            Tile::Goal(ObjectType::Empty) => write!(f, "\x1b[0;33m{}", GOAL_CHR),
-            Tile::Goal(ObjectType::Ball) => write!(f, "\x1b[0;33m{}", BALL_ON_GOAL_CHR),
-            Tile::Goal(obj) => write!(f, "{}", obj),
-        }
+        write!(f, "{}", Theme::default().style(self))
     }
 }
 /// Describes direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     Up,
     Down,
     Left,
     Right,
 }
+impl Direction {
+    /// Every direction, in the order of up, down, left and right
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+    /// Returns the direction which goes back.
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+    /// Converts direction to unit vector.
+    pub fn as_vector2(self) -> Vector2 {
+        match self {
+            Direction::Up => Vector2::new(0, -1),
+            Direction::Down => Vector2::new(0, 1),
+            Direction::Left => Vector2::new(-1, 0),
+            Direction::Right => Vector2::new(1, 0),
+        }
+    }
+}
+/// Returns index next to `index` in `direction` on a grid of `width` columns and `len` tiles,
+/// or `None` if it is out of the grid.
+pub(crate) fn neighbor(
+    index: usize,
+    direction: Direction,
+    width: usize,
+    len: usize,
+) -> Option<usize> {
+    let delta = direction.as_vector2();
+    let x = (index % width) as i32 + delta.get_x();
+    let y = (index / width) as i32 + delta.get_y();
+    if x < 0 || y < 0 || x as usize >= width {
+        return None;
+    }
+    let next = x as usize + y as usize * width;
+    if next < len {
+        Some(next)
+    } else {
+        None
+    }
+}
+
+/// Collects tiles which are connected to `starts` through tiles where `passable` is `true`.\
+/// Starts are always collected, and `passable` is checked once for each other tile.
+/// # Returns
+/// Indices of the tiles in breadth-first order
+pub(crate) fn flood(
+    starts: impl IntoIterator<Item = usize>,
+    width: usize,
+    len: usize,
+    mut passable: impl FnMut(usize) -> bool,
+) -> Vec<usize> {
+    let mut visited = vec![false; len];
+    let mut area = Vec::new();
+    for start in starts {
+        if !visited[start] {
+            visited[start] = true;
+            area.push(start);
+        }
+    }
+    let mut next = 0;
+    while next < area.len() {
+        let pos = area[next];
+        next += 1;
+        for &direction in &Direction::ALL {
+            if let Some(to) = neighbor(pos, direction, width, len) {
+                if !visited[to] {
+                    visited[to] = true;
+                    if passable(to) {
+                        area.push(to);
+                    }
+                }
+            }
+        }
+    }
+    area
+}
+
+/// Describes a move of player, which is stored in history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    /// Direction of the move
+    pub direction: Direction,
+    /// Whether a ball is pushed by the move
+    pub pushed: bool,
+}
 /// Describes game stage's state.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Stage {
     ///Stage's width
     width: usize,
@@ -101,164 +188,612 @@ pub struct Stage {
     total_goals: u32,
     ///Count of balls on goals
     matched_goals: u32,
+    ///Moves which can be undone
+    history: Vec<Move>,
+    ///Undone moves which can be redone
+    undone: Vec<Move>,
+    ///Squares from which a ball cannot reach any goal
+    dead_squares: Vec<bool>,
+    ///Whether the stage cannot be solved anymore
+    deadlocked: bool,
+    ///Whether the player pulls balls instead of pushing
+    reverse: bool,
+    ///Zobrist key of balls, which is updated on every move
+    balls_key: u64,
+    ///When the player moved first
+    started: Option<Instant>,
+    ///When the stage is won
+    finished: Option<Instant>,
 }
 
 impl Stage {
     ///Constructor of stage\
-    /// It reads text file as stage\
+    /// It reads text file as stage, whose format is detected by `StageFormat::detect`.\
     /// `WALL_CHR` is a wall, `EMPTY_CHR` is an empty plain, `GOAL_CHR` is goal, `PLAYER_CHR` is player on a plain
     /// # Example
     /// ```
-    /// use pusher::stage::ObjectType;
-    /// use pusher::stage::Tile;
-    /// use pusher::stage::Stage;
-    /// use pusher::vector2::Vector2;
-    /// let stage_data="#.@.O+#";
-    /// let stage=Stage::new(stage_data).unwrap();
+    /// use pusher::theme::Theme;
+    /// use pusher::viewport::Viewport;
+    /// use pusher::{Stage, Vector2};
+    /// let stage = Stage::new("#.@.O+#").unwrap();
+    /// assert_eq!(stage.player_position(), Vector2::new(2, 0));
+    /// assert_eq!((stage.total_goals(), stage.matched_goals()), (1, 0));
+    /// assert_eq!((stage.moves(), stage.pushes()), (0, 0));
+    /// let grid = Viewport::full(&stage).render(&stage, &Theme::plain(), None);
+    /// assert_eq!(grid, "#.@.O+#\r\n");
+    /// ```
+    /// # Errors
+    /// Same as `Stage::with_format`.
+    pub fn new(string: &str) -> Result<Stage, Error> {
+        Stage::with_format(string, StageFormat::detect(string))
+    }
+    ///Constructor of stage with explicit format.\
+    /// Shorter lines are padded with outside, and empty plains out of walls become outside.
+    /// # Example
+    /// ```
+    /// use pusher::error::Error;
+    /// use pusher::format::StageFormat;
+    /// use pusher::Stage;
+    /// let stage = Stage::with_format("#@$.*#", StageFormat::Xsb).unwrap();
     /// assert_eq!(
-    ///     format!("{:?}",stage),
-    ///     format!("Stage {{ \
-    /// width: 7, \
-    ///  height: 1, \
-    ///  data: [Wall, \
-    ///  Plain(Empty), \
-    ///  Plain(Player), \
-    ///  Plain(Empty), \
-    ///  Plain(Ball), \
-    ///  Goal(Empty), \
-    ///  Wall], \
-    ///  player_position: Vector2 {{ x: 2, y: 0 }}, \
-    ///  total_goals: 1, \
-    ///  matched_goals: 0 \
-    ///  }}")
+    ///     stage.to_format_string(StageFormat::Xsb).unwrap(),
+    ///     "#@$.*#\n"
     /// );
     /// assert_eq!(
-    ///     format!("{}",stage),
-    ///     "#.@.O+#\nMatched goal(s): 0/1\n"
+    ///     Stage::with_format("#@$.*#", StageFormat::Pusher).unwrap_err(),
+    ///     Error::InvalidChar { line: 1, col: 3, ch: '$' }
     /// );
     /// ```
     /// # Errors
-    /// It returns `Err(&'static str)` when:
-    /// * Invalid charactors are detected.
-    /// * Stage widths are inconsistent
-    /// * Stage width or height is 0
-    /// * Balls are not an many as goals.
-    /// * The number of Player is less or more than 1
-    pub fn new(string: &str) -> Result<Stage, &'static str> {
+    /// It returns `Err(Error)` when:
+    /// * Invalid charactors are detected. (`Error::InvalidChar`)
+    /// * Stage width or height is 0 (`Error::EmptyStage`)
+    /// * Balls are not an many as goals. (`Error::BallGoalMismatch`)
+    /// * The number of Player is less or more than 1 (`Error::InvalidPlayerCount`)
+    pub fn with_format(string: &str, format: StageFormat) -> Result<Stage, Error> {
         let mut data = Vec::new();
-        let mut width = 0;
+        let width = string
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
         let mut height = 0;
         let mut balls = 0;
         let mut total_goals = 0;
+        let mut matched_goals = 0;
         let mut player_position = Vector2::new(0, 0);
         let mut player_count = 0;
         for (y, line) in string.lines().enumerate() {
-            let line_width = line.len();
-            if y == 0 {
-                width = line_width;
-            } else if width != line_width {
-                return Err("Invalid Stage: Widths are inconsistent.");
-            }
             for (x, c) in line.chars().enumerate() {
-                match c {
-                    WALL_CHR => data.push(Tile::Wall),
-                    EMPTY_CHR => data.push(Tile::Plain(ObjectType::Empty)),
-                    BALL_CHR => {
+                let tile = format.parse_char(c).ok_or(Error::InvalidChar {
+                    line: y + 1,
+                    col: x + 1,
+                    ch: c,
+                })?;
+                match tile {
+                    Tile::Goal(_) => total_goals += 1,
+                    Tile::Wall | Tile::Plain(_) | Tile::Outside => (),
+                }
+                match tile {
+                    Tile::Goal(ObjectType::Ball) => {
                         balls += 1;
-                        data.push(Tile::Plain(ObjectType::Ball))
-                    }
-                    GOAL_CHR => {
-                        total_goals += 1;
-                        data.push(Tile::Goal(ObjectType::Empty))
+                        matched_goals += 1;
                     }
-                    PLAYER_CHR => {
+                    Tile::Plain(ObjectType::Ball) => balls += 1,
+                    Tile::Goal(ObjectType::Player) | Tile::Plain(ObjectType::Player) => {
                         player_position.set(x as i32, y as i32);
                         player_count += 1;
-                        data.push(Tile::Plain(ObjectType::Player))
                     }
-                    '\n' | '\r' => (),
-                    _ => return Err("Invalid Stage: invalid charactor detected."),
+                    _ => (),
                 }
+                data.push(tile);
             }
+            let padding = width - line.chars().count();
+            data.resize(data.len() + padding, Tile::Outside);
             height = y + 1;
         }
         if width == 0 || height == 0 {
-            return Err("Invalid Stage: Width or height is smaller than 1.");
+            return Err(Error::EmptyStage);
         }
         if balls != total_goals {
-            return Err("Invalid Stage: Balls are not as many as goals.");
+            return Err(Error::BallGoalMismatch {
+                balls,
+                goals: total_goals,
+            });
         }
         if player_count != 1 {
-            return Err("Invalid Stage: Player has to be only one.");
+            return Err(Error::InvalidPlayerCount {
+                count: player_count,
+            });
         }
-        Ok(Stage {
+        let player = player_position.get_x() as usize + player_position.get_y() as usize * width;
+        mark_outside(&mut data, width, player);
+        let dead_squares = deadlock::dead_squares(&data, width);
+        let balls_key = zobrist::balls_key(&data);
+        let mut stage = Stage {
             width,
             height,
             data,
             player_position,
             total_goals,
-            matched_goals: 0,
-        })
+            matched_goals,
+            history: Vec::new(),
+            undone: Vec::new(),
+            dead_squares,
+            deadlocked: false,
+            reverse: false,
+            balls_key,
+            started: None,
+            finished: None,
+        };
+        stage.deadlocked = stage.find_deadlock();
+        Ok(stage)
+    }
+    /// Serializes current state of the stage in `format`.\
+    /// Each line ends with `\n`, and outside at the end of a line is omitted.
+    /// # Errors
+    /// It returns `Err(Error::Unrepresentable)` when the format cannot represent a tile.
+    /// (e.g. A ball on the goal in `StageFormat::Pusher`)
+    pub fn to_format_string(&self, format: StageFormat) -> Result<String, Error> {
+        let mut string = String::with_capacity((self.width + 1) * self.height);
+        for (y, line) in self.data.chunks(self.width).enumerate() {
+            let end = line
+                .iter()
+                .rposition(|tile| tile != &Tile::Outside)
+                .map_or(0, |x| x + 1);
+            for (x, tile) in line[..end].iter().enumerate() {
+                string.push(format.tile_char(tile).ok_or(Error::Unrepresentable {
+                    format,
+                    line: y + 1,
+                    col: x + 1,
+                })?);
+            }
+            string.push('\n');
+        }
+        Ok(string)
     }
     /// Checks win state.\
     /// Return true when `self.matched_goals == self.total_goals`
     pub fn is_won(&self) -> bool {
         self.matched_goals == self.total_goals
     }
+    /// Checks the player pulls balls instead of pushing.
+    pub fn is_reverse(&self) -> bool {
+        self.reverse
+    }
+    /// Makes the reverse mode stage of the current state.\
+    /// Balls are put on the goals, and the squares where balls were become goals.
+    /// The player pulls balls, and wins when every ball is back on its start square.
+    /// If a ball is put on the player, the player moves to the nearest empty square.
+    /// # Example
+    /// ```
+    /// use pusher::{Direction, ObjectType, Stage, Tile, Vector2};
+    /// let stage = Stage::new("#@.O+#").unwrap();
+    /// let mut reverse = stage.reversed();
+    /// assert_eq!(reverse.tile(Vector2::new(4, 0)), Some(&Tile::Plain(ObjectType::Ball)));
+    /// assert!(!reverse.is_won());
+    /// reverse.move_player(Direction::Right).unwrap();
+    /// reverse.move_player(Direction::Right).unwrap();
+    /// reverse.move_player(Direction::Left).unwrap();
+    /// assert_eq!(reverse.tile(Vector2::new(3, 0)), Some(&Tile::Goal(ObjectType::Ball)));
+    /// assert!(reverse.is_won());
+    /// ```
+    pub fn reversed(&self) -> Stage {
+        let data: Vec<Tile> = self
+            .data
+            .iter()
+            .map(|tile| match tile {
+                Tile::Plain(ObjectType::Ball) => Tile::Goal(ObjectType::Empty),
+                Tile::Goal(ObjectType::Empty) | Tile::Goal(ObjectType::Player) => {
+                    Tile::Plain(ObjectType::Ball)
+                }
+                tile => tile.clone(),
+            })
+            .collect();
+        let balls_key = zobrist::balls_key(&data);
+        let mut stage = Stage {
+            matched_goals: data
+                .iter()
+                .filter(|&tile| tile == &Tile::Goal(ObjectType::Ball))
+                .count() as u32,
+            dead_squares: vec![false; data.len()],
+            data,
+            history: Vec::new(),
+            undone: Vec::new(),
+            deadlocked: false,
+            reverse: true,
+            balls_key,
+            started: None,
+            finished: None,
+            ..*self
+        };
+        let player = stage.vector2_as_index(stage.player_position).unwrap();
+        if stage.data[player].object() != Some(ObjectType::Player) {
+            let (width, len) = (stage.width, stage.data.len());
+            let data = &stage.data;
+            let empty = flood(Some(player), width, len, |pos| data[pos].object().is_some())
+                .into_iter()
+                .find(|&pos| data[pos].object() == Some(ObjectType::Empty));
+            if let Some(pos) = empty {
+                stage.data[pos] = match stage.data[pos] {
+                    Tile::Goal(_) => Tile::Goal(ObjectType::Player),
+                    _ => Tile::Plain(ObjectType::Player),
+                };
+                stage.player_position = Vector2::new((pos % width) as i32, (pos / width) as i32);
+            }
+        }
+        stage
+    }
+    /// Switches the stage to reverse mode without changing tiles.\
+    /// Reverse mode has no dead squares, so deadlocks are cleared.
+    pub(crate) fn into_reverse(self) -> Stage {
+        Stage {
+            dead_squares: vec![false; self.data.len()],
+            deadlocked: false,
+            reverse: true,
+            ..self
+        }
+    }
+    /// Checks the stage cannot be solved anymore.\
+    /// It is `true` if a ball is on a dead square or frozen out of goals.
+    pub fn is_deadlocked(&self) -> bool {
+        self.deadlocked
+    }
+    /// Checks a ball on `pos` can never reach any goal.
+    /// It is `false` if `pos` is out of the stage, like `tile` returns `None`.
+    pub fn is_dead_square(&self, pos: Vector2) -> bool {
+        self.vector2_as_index(pos)
+            .map_or(false, |index| self.dead_squares[index])
+    }
+    /// Checks the ball on `index` makes a deadlock.
+    /// Deadlocks are not detected in reverse mode.
+    fn is_deadlock_at(&self, index: usize) -> bool {
+        !self.reverse
+            && (self.dead_squares[index]
+                || deadlock::is_freeze_deadlock(&self.data, self.width, &self.dead_squares, index))
+    }
+    /// Checks all balls for a deadlock.
+    fn find_deadlock(&self) -> bool {
+        (0..self.data.len()).any(|i| {
+            matches!(self.data[i], Tile::Plain(ObjectType::Ball)) && self.is_deadlock_at(i)
+        })
+    }
     /// Moves player and pushes a ball (if it exists).\
-    /// it uses `self.move_object` to move.
+    /// In reverse mode, it pulls the ball behind the player instead.\
+    /// The move is recorded in history, and undone moves are discarded.
     /// # Errors
-    /// It returns `Err(&'static str)` when movement failed due to:
-    /// * car`self.move_object` has failed.
-    /// * Cannot push the blocking ball.
-    /// * Blocked by a wall.
-    /// * Blocked by boundary.
+    /// It returns `Err(Error)` when movement failed due to:
+    /// * `self.move_object` has failed.
+    /// * Cannot push the blocking ball. (`Error::BlockedByBall`)
+    /// * Blocked by a wall. (`Error::BlockedByWall`)
+    /// * Blocked by outside. (`Error::BlockedByBoundary`)
+    /// * Blocked by boundary. (`Error::BlockedByBoundary`)
     /// # Panics
     /// Panics if `self.player_position` is out of index.
-    pub fn move_player(&mut self, dir: Direction) -> Result<(), &'static str> {
-        let delta_pos = match dir {
-            Direction::Up => Vector2::new(0, -1),
-            Direction::Down => Vector2::new(0, 1),
-            Direction::Left => Vector2::new(-1, 0),
-            Direction::Right => Vector2::new(1, 0),
-        };
+    pub fn move_player(&mut self, dir: Direction) -> Result<(), Error> {
+        let pushed = self.step(dir)?;
+        self.history.push(Move {
+            direction: dir,
+            pushed,
+        });
+        self.undone.clear();
+        self.update_timer();
+        Ok(())
+    }
+    /// Takes back the last move.\
+    /// A pushed ball is pulled back to the previous position, and a pulled ball is pushed back.
+    /// # Errors
+    /// It returns `Err(Error)` when:
+    /// * There is no move to undo. (`Error::NothingToUndo`)
+    /// * `self.move_object` has failed.
+    pub fn undo(&mut self) -> Result<(), Error> {
+        let last = self.history.pop().ok_or(Error::NothingToUndo)?;
+        let delta_pos = last.direction.as_vector2();
+        let pos = self.vector2_as_index(self.player_position)?;
+        let prev_pos = self.vector2_as_index(self.player_position - delta_pos)?;
+        if self.reverse && last.pushed {
+            let ball_pos = self.vector2_as_index(self.player_position - delta_pos * 2)?;
+            self.move_ball(prev_pos, ball_pos)?;
+        }
+        self.move_object(pos, prev_pos)?;
+        self.player_position -= delta_pos;
+        if last.pushed && !self.reverse {
+            let ball_pos = self.vector2_as_index(self.player_position + delta_pos * 2)?;
+            if let Tile::Goal(ObjectType::Ball) = self.data[ball_pos] {
+                self.matched_goals -= 1;
+            }
+            self.move_object(ball_pos, pos)?;
+            if let Tile::Goal(ObjectType::Ball) = self.data[pos] {
+                self.matched_goals += 1;
+            }
+            self.deadlocked = self.find_deadlock();
+        }
+        self.finished = None;
+        self.undone.push(last);
+        Ok(())
+    }
+    /// Replays the last undone move.
+    /// # Errors
+    /// It returns `Err(Error)` when:
+    /// * There is no move to redo. (`Error::NothingToRedo`)
+    /// * The move has failed.
+    pub fn redo(&mut self) -> Result<(), Error> {
+        let next = self.undone.pop().ok_or(Error::NothingToRedo)?;
+        let pushed = self.step(next.direction)?;
+        self.history.push(Move {
+            direction: next.direction,
+            pushed,
+        });
+        self.update_timer();
+        Ok(())
+    }
+    /// Starts timer at the first move, and stops it when the stage is won.
+    fn update_timer(&mut self) {
+        let now = Instant::now();
+        self.started.get_or_insert(now);
+        self.finished = if self.is_won() { Some(now) } else { None };
+    }
+    /// Restarts timer as if `elapsed` has passed already.
+    pub fn resume_timer(&mut self, elapsed: Duration) {
+        let now = Instant::now();
+        self.started = Some(now.checked_sub(elapsed).unwrap_or(now));
+        self.finished = if self.is_won() { Some(now) } else { None };
+    }
+    /// Returns the number of moves, except undone moves.
+    pub fn moves(&self) -> usize {
+        self.history.len()
+    }
+    /// Returns the number of pushes, except undone moves.
+    pub fn pushes(&self) -> usize {
+        self.history.iter().filter(|m| m.pushed).count()
+    }
+    /// Returns time from the first move until now, or until the stage is won.
+    pub fn elapsed(&self) -> Duration {
+        match (self.started, self.finished) {
+            (Some(started), Some(finished)) => finished - started,
+            (Some(started), None) => started.elapsed(),
+            (None, _) => Duration::from_secs(0),
+        }
+    }
+    /// Summarizes moves, pushes and elapsed time.
+    /// # Example
+    /// ```
+    /// use pusher::{Direction, Stage};
+    /// let mut stage = Stage::new("#@.O+#").unwrap();
+    /// stage.move_player(Direction::Right).unwrap();
+    /// stage.move_player(Direction::Right).unwrap();
+    /// assert_eq!(stage.summary(), "Moves: 2, Pushes: 1, Time: 00:00");
+    /// ```
+    pub fn summary(&self) -> String {
+        let secs = self.elapsed().as_secs();
+        format!(
+            "Moves: {}, Pushes: {}, Time: {:02}:{:02}",
+            self.moves(),
+            self.pushes(),
+            secs / 60,
+            secs % 60
+        )
+    }
+    /// Describes matched goals and the summary in two lines.
+    pub fn status(&self) -> String {
+        format!(
+            "Matched goal(s): {}/{}\r\n{}\r\n",
+            self.matched_goals,
+            self.total_goals,
+            self.summary()
+        )
+    }
+    /// Returns moves from the beginning of the stage.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+    /// Returns undone moves. The last one is redone first.
+    pub fn undone(&self) -> &[Move] {
+        &self.undone
+    }
+    /// Getter of width
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// Getter of height
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Getter of player position
+    pub fn player_position(&self) -> Vector2 {
+        self.player_position
+    }
+    /// Getter of total count of goals
+    pub fn total_goals(&self) -> u32 {
+        self.total_goals
+    }
+    /// Getter of count of balls on goals
+    pub fn matched_goals(&self) -> u32 {
+        self.matched_goals
+    }
+    /// Returns the tile on `pos`, or `None` if `pos` is out of the stage.
+    pub fn tile(&self, pos: Vector2) -> Option<&Tile> {
+        self.vector2_as_index(pos)
+            .ok()
+            .map(|index| &self.data[index])
+    }
+    /// Iterates all tiles with their positions, row by row.
+    /// # Example
+    /// ```
+    /// use pusher::{ObjectType, Stage, Tile, Vector2};
+    /// let stage = Stage::new("#@O+#").unwrap();
+    /// let balls: Vec<Vector2> = stage
+    ///     .tiles()
+    ///     .filter(|(_, tile)| tile.object() == Some(ObjectType::Ball))
+    ///     .map(|(pos, _)| pos)
+    ///     .collect();
+    /// assert_eq!(balls, vec![Vector2::new(2, 0)]);
+    /// assert_eq!(stage.tile(Vector2::new(3, 0)), Some(&Tile::Goal(ObjectType::Empty)));
+    /// ```
+    pub fn tiles(&self) -> impl Iterator<Item = (Vector2, &Tile)> + '_ {
+        let width = self.width;
+        self.data
+            .iter()
+            .enumerate()
+            .map(move |(i, tile)| (Vector2::new((i % width) as i32, (i / width) as i32), tile))
+    }
+    /// Returns Zobrist key of the position, which identifies the player and balls.
+    /// # Panics
+    /// Panics if `self.player_position` is out of index.
+    pub fn zobrist_key(&self) -> u64 {
+        self.balls_key ^ zobrist::player_key(self.vector2_as_index(self.player_position).unwrap())
+    }
+    /// Returns the top-left square which the player can reach without pushing a ball.\
+    /// Positions whose players can walk to each other have the same normalized position.\
+    /// In reverse mode, a walk away from a ball pulls it, so it is the player position itself.
+    /// # Panics
+    /// Panics if `self.player_position` is out of index.
+    pub fn normalized_player_position(&self) -> Vector2 {
+        if self.reverse {
+            return self.player_position;
+        }
+        let start = self.vector2_as_index(self.player_position).unwrap();
+        let top_left = flood(Some(start), self.width, self.data.len(), |pos| {
+            self.data[pos].object() == Some(ObjectType::Empty)
+        })
+        .into_iter()
+        .min()
+        .unwrap_or(start);
+        Vector2::new(
+            (top_left % self.width) as i32,
+            (top_left / self.width) as i32,
+        )
+    }
+    /// Returns Zobrist key of the position with the normalized player position.\
+    /// It identifies positions which differ only by walking, such as in push-based searches.
+    /// # Example
+    /// ```
+    /// use pusher::{Direction, Stage};
+    /// let mut stage = Stage::new("#####\n#@..#\n#.O.#\n#..+#\n#####").unwrap();
+    /// let (key, canonical) = (stage.zobrist_key(), stage.canonical_key());
+    /// stage.move_player(Direction::Right).unwrap();
+    /// assert_ne!(stage.zobrist_key(), key);
+    /// assert_eq!(stage.canonical_key(), canonical);
+    /// stage.undo().unwrap();
+    /// assert_eq!(stage.zobrist_key(), key);
+    /// ```
+    pub fn canonical_key(&self) -> u64 {
+        let player = self.normalized_player_position();
+        self.balls_key ^ zobrist::player_key(self.vector2_as_index(player).unwrap())
+    }
+    /// Copies current state of the stage without history.
+    pub(crate) fn snapshot(&self) -> Stage {
+        Stage {
+            history: Vec::new(),
+            undone: Vec::new(),
+            data: self.data.clone(),
+            dead_squares: self.dead_squares.clone(),
+            started: None,
+            finished: None,
+            ..*self
+        }
+    }
+    /// Moves player and pushes a ball without recording history.
+    /// # Returns
+    /// `true` if a ball is pushed.
+    /// # Errors
+    /// Same as `self.move_player`.
+    pub(crate) fn step(&mut self, dir: Direction) -> Result<bool, Error> {
+        if self.reverse {
+            return self.pull(dir);
+        }
+        let delta_pos = dir.as_vector2();
         let pos = self.vector2_as_index(self.player_position).unwrap();
         let next_pos = self
             .vector2_as_index(self.player_position + delta_pos)
-            .map_err(|_err| "[Player] Blocked by boundary.")?;
+            .map_err(|_err| Error::BlockedByBoundary)?;
         match &self.data[next_pos] {
             Tile::Goal(ObjectType::Ball) | Tile::Plain(ObjectType::Ball) => {
                 let beyond_next_pos = self
                     .vector2_as_index(self.player_position + delta_pos * 2)
-                    .map_err(|_err| "[Player] Blocked by the Ball and boundary.")?;
+                    .map_err(|_err| Error::BlockedByBall)?;
                 self.move_object(next_pos, beyond_next_pos)
-                    .map_err(|_err| "[Player] Blocked by the Ball.")?;
-                self.move_object(pos, next_pos)?;
-                self.player_position += delta_pos;
+                    .map_err(|_err| Error::BlockedByBall)?;
                 if let Tile::Goal(ObjectType::Ball) = self.data[beyond_next_pos] {
                     self.matched_goals += 1;
                 }
-                Ok(())
+                if let Tile::Goal(_) = self.data[next_pos] {
+                    self.matched_goals -= 1;
+                }
+                self.move_object(pos, next_pos)?;
+                self.player_position += delta_pos;
+                if !self.deadlocked {
+                    self.deadlocked = self.is_deadlock_at(beyond_next_pos);
+                }
+                Ok(true)
             }
             Tile::Goal(_) | Tile::Plain(_) => {
                 self.move_object(pos, next_pos)?;
                 self.player_position += delta_pos;
-                Ok(())
+                Ok(false)
+            }
+            Tile::Wall => Err(Error::BlockedByWall),
+            Tile::Outside => Err(Error::BlockedByBoundary),
+        }
+    }
+    /// Moves player and pulls the ball behind the player (if it exists).
+    /// # Returns
+    /// `true` if a ball is pulled.
+    /// # Errors
+    /// It returns `Err(Error)` when the player is blocked by a wall, a ball or boundary.
+    fn pull(&mut self, dir: Direction) -> Result<bool, Error> {
+        let delta_pos = dir.as_vector2();
+        let pos = self.vector2_as_index(self.player_position).unwrap();
+        let next_pos = self
+            .vector2_as_index(self.player_position + delta_pos)
+            .map_err(|_err| Error::BlockedByBoundary)?;
+        match &self.data[next_pos] {
+            Tile::Goal(ObjectType::Ball) | Tile::Plain(ObjectType::Ball) => {
+                return Err(Error::BlockedByBall)
+            }
+            Tile::Wall => return Err(Error::BlockedByWall),
+            Tile::Outside => return Err(Error::BlockedByBoundary),
+            Tile::Goal(_) | Tile::Plain(_) => (),
+        }
+        self.move_object(pos, next_pos)?;
+        self.player_position += delta_pos;
+        let behind = self
+            .vector2_as_index(self.player_position - delta_pos * 2)
+            .ok()
+            .filter(|&behind| self.data[behind].object() == Some(ObjectType::Ball));
+        match behind {
+            Some(behind) => {
+                self.move_ball(behind, pos)?;
+                Ok(true)
             }
-            Tile::Wall => Err("[Player] Blocked by the Wall."),
+            None => Ok(false),
         }
     }
+    /// Moves a ball and counts matched goals again.
+    /// # Errors
+    /// Same as `self.move_object`.
+    fn move_ball(&mut self, from: usize, to: usize) -> Result<(), Error> {
+        self.move_object(from, to)?;
+        if self.data[from].is_goal() {
+            self.matched_goals -= 1;
+        }
+        if self.data[to].is_goal() {
+            self.matched_goals += 1;
+        }
+        Ok(())
+    }
     /// Moves Object from a Tile to an Empty Tile.
     /// # Errors
-    /// It returns `Err(&'static str)` when movement failed due to:
-    /// * Trying to move to same place.
-    /// * Trying to move a wall.
-    /// * Blocked by a wall.
-    /// * Destination tile is not empty.
-    fn move_object(&mut self, from: usize, to: usize) -> Result<(), &'static str> {
+    /// It returns `Err(Error)` when movement failed due to:
+    /// * Trying to move to same place. (`Error::SamePlace`)
+    /// * Trying to move a wall. (`Error::WallNotMovable`)
+    /// * Blocked by a wall. (`Error::BlockedByWall`)
+    /// * Blocked by outside. (`Error::BlockedByBoundary`)
+    /// * Destination tile is not empty. (`Error::DestinationOccupied`)
+    fn move_object(&mut self, from: usize, to: usize) -> Result<(), Error> {
         let (low, high) = match from.cmp(&to) {
             // no swapping necessary
-            std::cmp::Ordering::Equal => return Err("[Object] Trying to move to same place."),
+            std::cmp::Ordering::Equal => return Err(Error::SamePlace),
             // get the smallest and largest of the two indices
             std::cmp::Ordering::Less => (from, to),
             std::cmp::Ordering::Greater => (to, from),
@@ -276,72 +811,74 @@ impl Stage {
                 if let ObjectType::Empty = t_obj {
                     match from_tile {
                         Tile::Goal(ref mut f_obj) | Tile::Plain(ref mut f_obj) => {
+                            if let ObjectType::Ball = f_obj {
+                                self.balls_key ^= zobrist::ball_key(from) ^ zobrist::ball_key(to);
+                            }
                             *t_obj = *f_obj;
                             *f_obj = ObjectType::Empty;
                             Ok(())
                         }
-                        Tile::Wall => Err("[Object] Cannot move the Wall"),
+                        Tile::Wall | Tile::Outside => Err(Error::WallNotMovable),
                     }
                 } else {
-                    Err("[Object] Destination is not empty.")
+                    Err(Error::DestinationOccupied)
                 }
             }
-            Tile::Wall => Err("[Object] Blocked by the Wall."),
+            Tile::Wall => Err(Error::BlockedByWall),
+            Tile::Outside => Err(Error::BlockedByBoundary),
         }
     }
     /// Converts vector2 to 1d index.
     /// # Errors
-    /// It returns `Err(String)` when:
+    /// It returns `Err(Error::OutOfRange)` when:
     /// * x is bigger or equal than width of stage, or y is bigger or equal than height of stage
-    /// * either x or y is incompatible with usize.  
-    fn vector2_as_index(&self, v: Vector2) -> Result<usize, String> {
-        let x: usize = v
-            .get_x()
-            .try_into()
-            .map_err(|_err| String::from("x cannot be converted to usize."))?;
-        let y: usize = v
-            .get_y()
-            .try_into()
-            .map_err(|_err| String::from("y cannot be converted to usize."))?;
+    /// * either x or y is incompatible with usize.
+    fn vector2_as_index(&self, v: Vector2) -> Result<usize, Error> {
+        let out_of_range = Error::OutOfRange {
+            pos: v,
+            width: self.width,
+            height: self.height,
+        };
+        let x: usize = v.get_x().try_into().map_err(|_err| out_of_range.clone())?;
+        let y: usize = v.get_y().try_into().map_err(|_err| out_of_range.clone())?;
         if x < self.width && y < self.height {
             Ok(x + y * self.width)
         } else {
-            Err(format!(
-                "Index out of range! got Vector 2 {} while w:h={}:{}.",
-                v, self.width, self.height
-            ))
+            Err(out_of_range)
         }
     }
 }
 
-impl fmt::Display for Stage {
-    #[cfg(not(feature = "color"))]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for line in (&self.data).chunks(self.width) {
-            for c in line {
-                write!(f, "{}", c)?;
-            }
-            write!(f, "\r\n")?;
-        }
-        write!(
-            f,
-            "Matched goal(s): {}/{}\r\n",
-            self.matched_goals, self.total_goals
-        )
+/// Turns empty plains out of walls into outside.\
+/// They are connected to the edge of the stage or padding, and the player at `player` cannot reach them.
+fn mark_outside(data: &mut [Tile], width: usize, player: usize) {
+    let len = data.len();
+    let mut reachable = vec![false; len];
+    for pos in flood(Some(player), width, len, |pos| {
+        !matches!(data[pos], Tile::Wall | Tile::Outside)
+    }) {
+        reachable[pos] = true;
     }
-    #[cfg(feature = "color")]
+    let is_outside = |pos: usize| match data[pos] {
+        Tile::Plain(ObjectType::Empty) => !reachable[pos],
+        Tile::Outside => true,
+        _ => false,
+    };
+    let edges = (0..len).filter(|&i| {
+        let (x, y) = (i % width, i / width);
+        x == 0 || y == 0 || x + 1 == width || y + 1 == len / width || data[i] == Tile::Outside
+    });
+    let outside = flood(edges.filter(|&i| is_outside(i)), width, len, is_outside);
+    for pos in outside {
+        data[pos] = Tile::Outside;
+    }
+}
+
+impl fmt::Display for Stage {
+    /// Writes the whole stage in the default theme and its status.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for line in (&self.data).chunks(self.width) {
-            for c in line {
-                write!(f, "{}", c)?;
-            }
-            write!(f, "\r\n")?;
-        }
-        write!(
-            f,
-            "\x1b[0mMatched goal(s): {}/{}\r\n",
-            self.matched_goals, self.total_goals
-        )
+        let grid = Viewport::full(self).render(self, &Theme::default(), None);
+        write!(f, "{}{}", grid, self.status())
     }
 }
 
@@ -351,16 +888,207 @@ impl ops::Index<Vector2> for Stage {
     fn index(&self, point: Vector2) -> &Self::Output {
         match self.vector2_as_index(point) {
             Ok(index) => &self.data[index],
-            Err(msg) => panic!("{}",msg),
+            Err(err) => panic!("{}", err),
         }
     }
 }
 
-impl ops::IndexMut<Vector2> for Stage {
-    fn index_mut(&mut self, point: Vector2) -> &mut Tile {
-        match self.vector2_as_index(point) {
-            Ok(index) => &mut self.data[index],
-            Err(msg) => panic!("{}",msg),
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn flooding() {
+        // 3x3 grid with walls on the middle column except the bottom
+        let walls = [false, true, false, false, true, false, false, false, false];
+        assert_eq!(flood(Some(0), 3, 9, |i| !walls[i]), [0, 3, 6, 7, 8, 5, 2]);
+        assert_eq!(flood(vec![1, 1], 3, 9, |_| false), [1]);
+        assert_eq!(neighbor(2, Direction::Right, 3, 9), None);
+        assert_eq!(neighbor(7, Direction::Down, 3, 9), None);
+    }
+    #[test]
+    fn undo_push() {
+        let mut s = Stage::new("#@O+#").unwrap();
+        s.move_player(Direction::Right).unwrap();
+        assert!(s.is_won());
+        assert_eq!(
+            s.history(),
+            &[Move {
+                direction: Direction::Right,
+                pushed: true
+            }]
+        );
+        s.undo().unwrap();
+        assert!(!s.is_won());
+        assert_eq!(s.player_position, Vector2::new(1, 0));
+        assert_eq!(
+            format!("{:?}", s.data),
+            "[Wall, Plain(Player), Plain(Ball), Goal(Empty), Wall]"
+        );
+        assert!(s.undo().is_err());
+    }
+    #[test]
+    fn reverse() {
+        let mut s = Stage::new("#.O+@.#").unwrap().reversed();
+        assert!(s.is_reverse());
+        assert_eq!(s.move_player(Direction::Left), Err(Error::BlockedByBall));
+        s.move_player(Direction::Right).unwrap();
+        assert!(s.history()[0].pushed);
+        assert_eq!(
+            format!("{:?}", s.data),
+            "[Wall, Plain(Empty), Goal(Empty), Plain(Empty), Plain(Ball), Plain(Player), Wall]"
+        );
+        s.undo().unwrap();
+        assert_eq!(s.player_position, Vector2::new(4, 0));
+        assert_eq!(
+            format!("{:?}", s.data),
+            "[Wall, Plain(Empty), Goal(Empty), Plain(Ball), Plain(Player), Plain(Empty), Wall]"
+        );
+        let s = Stage::with_format("#$ +#", StageFormat::Xsb)
+            .unwrap()
+            .reversed();
+        assert_eq!(s.player_position, Vector2::new(2, 0));
+        assert_eq!(
+            format!("{:?}", s.data),
+            "[Wall, Goal(Empty), Plain(Player), Plain(Ball), Wall]"
+        );
+        assert!(!s.is_deadlocked());
+    }
+    #[test]
+    fn zobrist() {
+        let mut s = Stage::new("######\n#@...#\n#.OO.#\n#.++.#\n######").unwrap();
+        let start = s.zobrist_key();
+        for &d in &[
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+            Direction::Up,
+        ] {
+            let _ = s.move_player(d);
+            assert_eq!(s.balls_key, zobrist::balls_key(&s.data));
         }
+        while s.undo().is_ok() {}
+        assert_eq!(s.zobrist_key(), start);
+        assert_eq!(s.normalized_player_position(), Vector2::new(1, 1));
+        let reverse = s.reversed();
+        assert_eq!(reverse.balls_key, zobrist::balls_key(&reverse.data));
+    }
+    #[test]
+    fn reverse_canonical_key() {
+        // Walking left from beside the ball pulls it, so the positions are different.
+        let beside = Stage::new("######\n#.@O+#\n######").unwrap().into_reverse();
+        let away = Stage::new("######\n#@.O+#\n######").unwrap().into_reverse();
+        assert_eq!(beside.normalized_player_position(), Vector2::new(2, 1));
+        assert_eq!(beside.canonical_key(), beside.zobrist_key());
+        assert_ne!(beside.canonical_key(), away.canonical_key());
+    }
+    #[test]
+    fn redo() {
+        let mut s = Stage::new("#@.O+.#").unwrap();
+        s.move_player(Direction::Right).unwrap();
+        s.move_player(Direction::Right).unwrap();
+        s.move_player(Direction::Right).unwrap();
+        assert_eq!(s.matched_goals, 0);
+        s.undo().unwrap();
+        s.undo().unwrap();
+        s.redo().unwrap();
+        s.redo().unwrap();
+        assert_eq!(s.matched_goals, 0);
+        assert_eq!(s.player_position, Vector2::new(4, 0));
+        assert_eq!(
+            format!("{:?}", s.data[3..6].to_vec()),
+            "[Plain(Empty), Goal(Player), Plain(Ball)]"
+        );
+        assert!(s.redo().is_err());
+        s.undo().unwrap();
+        s.move_player(Direction::Left).unwrap();
+        assert!(s.redo().is_err());
+    }
+    #[test]
+    fn deadlock() {
+        let mut s = Stage::new("######\n#....#\n#.O@.#\n#...+#\n######").unwrap();
+        assert!(!s.is_deadlocked());
+        assert!(s.is_dead_square(Vector2::new(1, 1)));
+        assert!(!s.is_dead_square(Vector2::new(2, 2)));
+        assert!(!s.is_dead_square(Vector2::new(-1, 0)));
+        assert!(!s.is_dead_square(Vector2::new(6, 4)));
+        s.move_player(Direction::Left).unwrap();
+        assert!(s.is_deadlocked());
+        s.undo().unwrap();
+        assert!(!s.is_deadlocked());
+    }
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Stage::new("#@O+#\n#.x.#").unwrap_err(),
+            Error::InvalidChar {
+                line: 2,
+                col: 3,
+                ch: 'x'
+            }
+        );
+        assert_eq!(
+            Stage::new("#@OO+#").unwrap_err(),
+            Error::BallGoalMismatch { balls: 2, goals: 1 }
+        );
+        assert_eq!(
+            Stage::new("#@O+@#").unwrap_err(),
+            Error::InvalidPlayerCount { count: 2 }
+        );
+        assert_eq!(Stage::new("").unwrap_err(), Error::EmptyStage);
+    }
+    #[test]
+    fn ragged() {
+        let mut s = Stage::new("  ####\n###  #\n#@ $ #\n#. ###\n####").unwrap();
+        assert_eq!((s.width(), s.height()), (6, 5));
+        assert_eq!(s.tile(Vector2::new(0, 0)), Some(&Tile::Outside));
+        assert_eq!(s.tile(Vector2::new(5, 4)), Some(&Tile::Outside));
+        assert_eq!(
+            s.tile(Vector2::new(3, 1)),
+            Some(&Tile::Plain(ObjectType::Empty))
+        );
+        assert_eq!(
+            s.to_format_string(StageFormat::Xsb).unwrap(),
+            "  ####\n###  #\n#@ $ #\n#. ###\n####\n"
+        );
+        let mut open = Stage::new("#@ $.").unwrap();
+        assert_eq!(
+            open.tile(Vector2::new(2, 0)),
+            Some(&Tile::Plain(ObjectType::Empty))
+        );
+        assert_eq!(
+            open.move_player(Direction::Up),
+            Err(Error::BlockedByBoundary)
+        );
+        let mut short = Stage::new("#####\n#@$.\n####").unwrap();
+        assert_eq!(short.tile(Vector2::new(4, 1)), Some(&Tile::Outside));
+        short.move_player(Direction::Right).unwrap();
+        assert_eq!(
+            short.move_player(Direction::Right),
+            Err(Error::BlockedByBall)
+        );
+        s.move_player(Direction::Right).unwrap();
+        assert_eq!(s.move_player(Direction::Right), Ok(()));
+        let mut s = Stage::new("@O+#").unwrap();
+        assert_eq!(
+            s.move_player(Direction::Left),
+            Err(Error::BlockedByBoundary)
+        );
+        assert_eq!(s.move_player(Direction::Up), Err(Error::BlockedByBoundary));
+        s.move_player(Direction::Right).unwrap();
+        assert_eq!(s.move_player(Direction::Right), Err(Error::BlockedByBall));
+    }
+    #[test]
+    fn counters() {
+        let mut s = Stage::new("#@.O+.#").unwrap();
+        assert_eq!((s.moves(), s.pushes()), (0, 0));
+        assert_eq!(s.elapsed(), Duration::from_secs(0));
+        s.move_player(Direction::Right).unwrap();
+        s.move_player(Direction::Right).unwrap();
+        assert_eq!((s.moves(), s.pushes()), (2, 1));
+        assert!(s.finished.is_some());
+        s.undo().unwrap();
+        assert_eq!((s.moves(), s.pushes()), (1, 0));
+        assert!(s.started.is_some());
+        assert!(s.finished.is_none());
     }
 }
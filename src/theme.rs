@@ -0,0 +1,353 @@
+//! Theme for pusher
+//!
+//! A theme decides glyphs and colors of tiles, and colors of messages.
+//! A theme file has `key: glyph [color]` lines, and lines starting with `;` are comments.
+//! Colors are ANSI SGR parameters, and glyphs with spaces are quoted.
+//! Message keys take a color only.
+//! ```text
+//! ; Keys: wall, floor, ball, goal, ball_on_goal, player, player_on_goal, outside
+//! ; Message keys: error, warning, won
+//! wall: 🧱
+//! floor: "  "
+//! ball: ○ 0;37
+//! error: 1;31
+//! ```
+//! Every glyph of a theme has to be the same width, so double-width skins replace all glyphs.
+
+use super::error::Error;
+use super::stage::{ObjectType, Tile};
+use super::stage::{BALL_CHR, BALL_ON_GOAL_CHR, EMPTY_CHR, GOAL_CHR, PLAYER_CHR, WALL_CHR};
+use std::fmt;
+
+/// Names of built-in themes.
+pub const BUILTIN_THEMES: [&str; 3] = ["plain", "color", "emoji"];
+
+/// Describes a glyph and its color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+    /// Text of the tile
+    pub glyph: String,
+    /// ANSI SGR parameters such as `1;31`, `None` for no color
+    pub color: Option<String>,
+}
+
+impl Style {
+    /// Makes a style from a glyph and a color.
+    fn new(glyph: &str, color: Option<&str>) -> Style {
+        Style {
+            glyph: String::from(glyph),
+            color: color.map(String::from),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.color {
+            Some(color) => write!(f, "\x1b[{}m{}", color, self.glyph),
+            None => write!(f, "{}", self.glyph),
+        }
+    }
+}
+
+/// Describes styles of every kind of tile and colors of messages.\
+/// A message color of `None` means no color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub wall: Style,
+    pub floor: Style,
+    pub ball: Style,
+    pub goal: Style,
+    pub ball_on_goal: Style,
+    pub player: Style,
+    pub player_on_goal: Style,
+    pub outside: Style,
+    /// Color of error messages
+    pub error: Option<String>,
+    /// Color of warning messages
+    pub warning: Option<String>,
+    /// Color of messages of winning
+    pub won: Option<String>,
+}
+
+impl Theme {
+    /// Makes the theme without colors, which uses characters of pusher format.
+    pub fn plain() -> Theme {
+        let style = |c: char| Style::new(&c.to_string(), None);
+        Theme {
+            wall: style(WALL_CHR),
+            floor: style(EMPTY_CHR),
+            ball: style(BALL_CHR),
+            goal: style(GOAL_CHR),
+            ball_on_goal: style(BALL_ON_GOAL_CHR),
+            player: style(PLAYER_CHR),
+            player_on_goal: style(PLAYER_CHR),
+            outside: Style::new(" ", None),
+            error: None,
+            warning: None,
+            won: None,
+        }
+    }
+    /// Makes the theme with ANSI colors.
+    pub fn color() -> Theme {
+        let style = |c: char, color| Style::new(&c.to_string(), Some(color));
+        Theme {
+            wall: style(WALL_CHR, "1;31"),
+            floor: style(EMPTY_CHR, "1;30"),
+            ball: style(BALL_CHR, "0;37"),
+            goal: style(GOAL_CHR, "0;33"),
+            ball_on_goal: style(BALL_ON_GOAL_CHR, "0;33"),
+            player: style(PLAYER_CHR, "0;32"),
+            player_on_goal: style(PLAYER_CHR, "0;32"),
+            outside: Style::new(" ", None),
+            error: Some(String::from("0;31")),
+            warning: Some(String::from("0;35")),
+            won: Some(String::from("0;33")),
+        }
+    }
+    /// Makes the theme of double-width emoji, whose messages have no color.
+    pub fn emoji() -> Theme {
+        Theme {
+            wall: Style::new("🧱", None),
+            floor: Style::new("  ", None),
+            ball: Style::new("📦", None),
+            goal: Style::new("🎯", None),
+            ball_on_goal: Style::new("✅", None),
+            player: Style::new("🙂", None),
+            player_on_goal: Style::new("😀", None),
+            outside: Style::new("  ", None),
+            error: None,
+            warning: None,
+            won: None,
+        }
+    }
+    /// Finds a built-in theme by the name.
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "plain" => Some(Theme::plain()),
+            "color" => Some(Theme::color()),
+            "emoji" => Some(Theme::emoji()),
+            _ => None,
+        }
+    }
+    /// Parses a theme file. Keys which are not given are same as the plain theme.
+    /// # Example
+    /// ```
+    /// use pusher::theme::Theme;
+    /// let theme = Theme::parse("; dots\nfloor: \" \"\nwall: # 1;34\n").unwrap();
+    /// assert_eq!(theme.floor.glyph, " ");
+    /// assert_eq!(theme.wall.color.as_deref(), Some("1;34"));
+    /// assert_eq!(theme.ball, Theme::plain().ball);
+    /// ```
+    /// # Errors
+    /// It returns `Err(Error)` when:
+    /// * A line is not `key: glyph [color]` (`key: color` for messages),
+    ///   the key is unknown, or a glyph takes no column. (`Error::InvalidTheme`)
+    /// * Glyphs are not the same width. (`Error::GlyphWidthMismatch`)
+    pub fn parse(string: &str) -> Result<Theme, Error> {
+        let mut theme = Theme::plain();
+        for (index, line) in string.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let invalid = Error::InvalidTheme { line: index + 1 };
+            let colon = line.find(':').ok_or_else(|| invalid.clone())?;
+            let (key, value) = (line[..colon].trim(), line[colon + 1..].trim());
+            let message = match key {
+                "error" => Some(&mut theme.error),
+                "warning" => Some(&mut theme.warning),
+                "won" => Some(&mut theme.won),
+                _ => None,
+            };
+            if let Some(message) = message {
+                if value.is_empty() || !is_color(value) {
+                    return Err(invalid);
+                }
+                *message = Some(String::from(value));
+                continue;
+            }
+            let style = parse_style(value).ok_or_else(|| invalid.clone())?;
+            match key {
+                "wall" => theme.wall = style,
+                "floor" => theme.floor = style,
+                "ball" => theme.ball = style,
+                "goal" => theme.goal = style,
+                "ball_on_goal" => theme.ball_on_goal = style,
+                "player" => theme.player = style,
+                "player_on_goal" => theme.player_on_goal = style,
+                "outside" => theme.outside = style,
+                _ => return Err(invalid),
+            }
+        }
+        let width = theme.width();
+        if theme
+            .styles()
+            .iter()
+            .any(|s| glyph_width(&s.glyph) != width)
+        {
+            return Err(Error::GlyphWidthMismatch);
+        }
+        Ok(theme)
+    }
+    /// Returns the style of the tile.
+    pub fn style(&self, tile: &Tile) -> &Style {
+        match tile {
+            Tile::Wall => &self.wall,
+            Tile::Plain(obj) => self.object_style(*obj),
+            Tile::Goal(ObjectType::Empty) => &self.goal,
+            Tile::Goal(ObjectType::Ball) => &self.ball_on_goal,
+            Tile::Goal(ObjectType::Player) => &self.player_on_goal,
+            Tile::Outside => &self.outside,
+        }
+    }
+    /// Returns the style of the object on a plain.
+    pub fn object_style(&self, obj: ObjectType) -> &Style {
+        match obj {
+            ObjectType::Empty => &self.floor,
+            ObjectType::Ball => &self.ball,
+            ObjectType::Player => &self.player,
+        }
+    }
+    /// Returns columns of a tile on the terminal.
+    pub fn width(&self) -> usize {
+        glyph_width(&self.wall.glyph)
+    }
+    /// Checks any style has a color, which has to be reset after rendering.
+    pub fn is_colored(&self) -> bool {
+        self.styles().iter().any(|s| s.color.is_some())
+    }
+    /// Returns all styles.
+    fn styles(&self) -> [&Style; 8] {
+        [
+            &self.wall,
+            &self.floor,
+            &self.ball,
+            &self.goal,
+            &self.ball_on_goal,
+            &self.player,
+            &self.player_on_goal,
+            &self.outside,
+        ]
+    }
+}
+
+impl Default for Theme {
+    /// The color theme with `color` feature, or the plain theme.
+    fn default() -> Theme {
+        if cfg!(feature = "color") {
+            Theme::color()
+        } else {
+            Theme::plain()
+        }
+    }
+}
+
+/// Parses `glyph [color]`. A glyph in double quotes can have spaces.\
+/// A glyph which takes no column, such as a lone joiner, is rejected.
+fn parse_style(value: &str) -> Option<Style> {
+    let (glyph, rest) = if let Some(quoted) = value.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        (&quoted[..end], &quoted[end + 1..])
+    } else {
+        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+        (&value[..end], &value[end..])
+    };
+    let color = rest.trim();
+    if glyph_width(glyph) == 0 || !is_color(color) {
+        return None;
+    }
+    Some(Style::new(glyph, Some(color).filter(|c| !c.is_empty())))
+}
+
+/// Checks `color` consists of ANSI SGR parameters only.
+fn is_color(color: &str) -> bool {
+    color.chars().all(|c| c.is_ascii_digit() || c == ';')
+}
+
+/// Estimates columns of `glyph` on the terminal.\
+/// East Asian wide characters and emoji take 2 columns, and joiners take none.
+pub fn glyph_width(glyph: &str) -> usize {
+    glyph
+        .chars()
+        .map(|c| match c as u32 {
+            0x200D | 0xFE00..=0xFE0F => 0,
+            0x1100..=0x115F
+            | 0x2705
+            | 0x2B1B..=0x2B1C
+            | 0x2B50
+            | 0x2B55
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn builtin() {
+        for name in BUILTIN_THEMES.iter() {
+            let theme = Theme::builtin(name).unwrap();
+            let width = theme.width();
+            assert!(theme
+                .styles()
+                .iter()
+                .all(|s| glyph_width(&s.glyph) == width));
+        }
+        assert_eq!(Theme::emoji().width(), 2);
+        assert_eq!(Theme::builtin("neon"), None);
+        assert_eq!(Theme::color().style(&Tile::Wall).to_string(), "\x1b[1;31m#");
+        assert!(!Theme::plain().is_colored());
+        assert_eq!(Theme::plain().error, None);
+        assert_eq!(Theme::color().error.as_deref(), Some("0;31"));
+    }
+    #[test]
+    fn message_colors() {
+        let theme = Theme::parse("error: 1;31\nwon: 0;33\n").unwrap();
+        assert_eq!(theme.error.as_deref(), Some("1;31"));
+        assert_eq!((theme.warning, theme.won.as_deref()), (None, Some("0;33")));
+        assert_eq!(
+            Theme::parse("error: X 1;31"),
+            Err(Error::InvalidTheme { line: 1 })
+        );
+        assert_eq!(Theme::parse("won:"), Err(Error::InvalidTheme { line: 1 }));
+    }
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Theme::parse("wall: #\nroof: ^\n"),
+            Err(Error::InvalidTheme { line: 2 })
+        );
+        assert_eq!(
+            Theme::parse("wall: # red"),
+            Err(Error::InvalidTheme { line: 1 })
+        );
+        assert_eq!(
+            Theme::parse("wall: \"#"),
+            Err(Error::InvalidTheme { line: 1 })
+        );
+        assert_eq!(Theme::parse("wall: 🧱"), Err(Error::GlyphWidthMismatch));
+        assert_eq!(
+            Theme::parse("wall: 벽").unwrap_err(),
+            Error::GlyphWidthMismatch
+        );
+        assert_eq!(
+            Theme::parse("wall: #\nfloor: \u{200D}"),
+            Err(Error::InvalidTheme { line: 2 })
+        );
+        assert_eq!(
+            Theme::parse("wall: \"\u{FE0F}\" 1;31"),
+            Err(Error::InvalidTheme { line: 1 })
+        );
+    }
+}
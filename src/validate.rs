@@ -0,0 +1,249 @@
+//! Level validator for pusher
+//!
+//! It reports every problem of a stage at once, instead of the first error of `Stage::with_format`.
+//! Errors make the stage unplayable, and warnings point out suspicious parts of a playable stage.
+
+use super::error::Error;
+use super::format::StageFormat;
+use super::stage::{flood, Direction, ObjectType, Stage, Tile};
+use super::vector2::Vector2;
+use std::fmt;
+
+/// Describes a suspicious part of a playable stage. Lines and columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// Player can never reach the area which starts at the position.
+    Unreachable { line: usize, col: usize },
+    /// Ball cannot reach any goal from the position.
+    BallOnDeadSquare { line: usize, col: usize },
+    /// Player can walk out of the stage at the position, which is not surrounded by walls.
+    OpenToOutside { line: usize, col: usize },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::Unreachable { line, col } => {
+                write!(f, "Area from line {}, column {} is unreachable.", line, col)
+            }
+            Warning::BallOnDeadSquare { line, col } => write!(
+                f,
+                "Ball at line {}, column {} cannot reach any goal.",
+                line, col
+            ),
+            Warning::OpenToOutside { line, col } => write!(
+                f,
+                "Stage is open to the outside at line {}, column {}.",
+                line, col
+            ),
+        }
+    }
+}
+
+/// Describes a problem of a stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The stage cannot be played.
+    Error(Error),
+    /// The stage can be played but it is suspicious.
+    Warning(Warning),
+}
+
+impl Issue {
+    /// Checks the issue is an error.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Issue::Error(_))
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Error(err) => write!(f, "error: {}", err),
+            Issue::Warning(warning) => write!(f, "warning: {}", warning),
+        }
+    }
+}
+
+/// Finds all errors of the stage, or warnings if it has no error.\
+/// Lines are counted from `first_line` (1-based), which is the line of the stage in its file.
+/// # Example
+/// ```
+/// use pusher::error::Error;
+/// use pusher::format::StageFormat;
+/// use pusher::validate::{validate, Issue};
+/// let issues = validate("#@Ox#\n#.y+#\n", StageFormat::Pusher, 10);
+/// assert_eq!(
+///     issues,
+///     vec![
+///         Issue::Error(Error::InvalidChar { line: 10, col: 4, ch: 'x' }),
+///         Issue::Error(Error::InvalidChar { line: 11, col: 3, ch: 'y' }),
+///     ]
+/// );
+/// ```
+pub fn validate(string: &str, format: StageFormat, first_line: usize) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut width = 0;
+    let (mut balls, mut goals, mut players) = (0, 0, 0);
+    for (y, line) in string.lines().enumerate() {
+        let line_number = first_line + y;
+        width = width.max(line.chars().count());
+        for (x, c) in line.chars().enumerate() {
+            match format.parse_char(c) {
+                Some(tile) => {
+                    goals += tile.is_goal() as u32;
+                    match tile.object() {
+                        Some(ObjectType::Ball) => balls += 1,
+                        Some(ObjectType::Player) => players += 1,
+                        _ => (),
+                    }
+                }
+                None => issues.push(Issue::Error(Error::InvalidChar {
+                    line: line_number,
+                    col: x + 1,
+                    ch: c,
+                })),
+            }
+        }
+    }
+    if width == 0 {
+        issues.push(Issue::Error(Error::EmptyStage));
+    }
+    if balls != goals {
+        issues.push(Issue::Error(Error::BallGoalMismatch { balls, goals }));
+    }
+    if players != 1 {
+        issues.push(Issue::Error(Error::InvalidPlayerCount { count: players }));
+    }
+    if issues.is_empty() {
+        match Stage::with_format(string, format) {
+            Ok(stage) => {
+                issues.extend(warnings(&stage, first_line).into_iter().map(Issue::Warning))
+            }
+            Err(err) => issues.push(Issue::Error(err)),
+        }
+    }
+    issues
+}
+
+/// Finds warnings of a playable stage.
+fn warnings(stage: &Stage, first_line: usize) -> Vec<Warning> {
+    let position = |pos: Vector2| (first_line + pos.get_y() as usize, pos.get_x() as usize + 1);
+    let reachable = area(stage, stage.player_position());
+    let mut warnings = Vec::new();
+    if let Some(&pos) = reachable.iter().find(|&&pos| is_open(stage, pos)) {
+        let (line, col) = position(pos);
+        warnings.push(Warning::OpenToOutside { line, col });
+    }
+    let mut visited = vec![false; stage.width() * stage.height()];
+    for &pos in &reachable {
+        visited[index(stage, pos)] = true;
+    }
+    for (pos, tile) in stage.tiles() {
+        if matches!(tile, Tile::Wall | Tile::Outside) || visited[index(stage, pos)] {
+            continue;
+        }
+        for pos in area(stage, pos) {
+            visited[index(stage, pos)] = true;
+        }
+        let (line, col) = position(pos);
+        warnings.push(Warning::Unreachable { line, col });
+    }
+    for (pos, tile) in stage.tiles() {
+        if tile == &Tile::Plain(ObjectType::Ball) && stage.is_dead_square(pos) {
+            let (line, col) = position(pos);
+            warnings.push(Warning::BallOnDeadSquare { line, col });
+        }
+    }
+    warnings
+}
+
+/// Collects tiles which are connected to `start` without walls and outside.
+fn area(stage: &Stage, start: Vector2) -> Vec<Vector2> {
+    let width = stage.width();
+    let at = |i: usize| Vector2::new((i % width) as i32, (i / width) as i32);
+    let passable = |i: usize| {
+        !matches!(
+            stage.tile(at(i)),
+            Some(Tile::Wall) | Some(Tile::Outside) | None
+        )
+    };
+    flood(
+        Some(index(stage, start)),
+        width,
+        width * stage.height(),
+        passable,
+    )
+    .into_iter()
+    .map(at)
+    .collect()
+}
+
+/// Checks `pos` is on the edge of the stage or next to outside.
+fn is_open(stage: &Stage, pos: Vector2) -> bool {
+    Direction::ALL.iter().any(|direction| {
+        matches!(
+            stage.tile(pos + direction.as_vector2()),
+            Some(Tile::Outside) | None
+        )
+    })
+}
+
+/// Converts a position in the stage to an index of the tiles.
+fn index(stage: &Stage, pos: Vector2) -> usize {
+    pos.get_x() as usize + pos.get_y() as usize * stage.width()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn all_errors() {
+        let issues = validate("#@@x\n#OO\n#y#+\n", StageFormat::Pusher, 1);
+        assert_eq!(
+            issues,
+            vec![
+                Issue::Error(Error::InvalidChar {
+                    line: 1,
+                    col: 4,
+                    ch: 'x'
+                }),
+                Issue::Error(Error::InvalidChar {
+                    line: 3,
+                    col: 2,
+                    ch: 'y'
+                }),
+                Issue::Error(Error::BallGoalMismatch { balls: 2, goals: 1 }),
+                Issue::Error(Error::InvalidPlayerCount { count: 2 }),
+            ]
+        );
+        assert!(issues.iter().all(Issue::is_error));
+    }
+    #[test]
+    fn warnings() {
+        let issues = validate(
+            "  #####\n  #@ $ \n  ### #\n#####.#\n# #$#.#\n#######\n",
+            StageFormat::Xsb,
+            1,
+        );
+        assert_eq!(
+            issues,
+            vec![
+                Issue::Warning(Warning::OpenToOutside { line: 2, col: 7 }),
+                Issue::Warning(Warning::Unreachable { line: 5, col: 2 }),
+                Issue::Warning(Warning::Unreachable { line: 5, col: 4 }),
+                Issue::Warning(Warning::BallOnDeadSquare { line: 2, col: 6 }),
+                Issue::Warning(Warning::BallOnDeadSquare { line: 5, col: 4 }),
+            ]
+        );
+        let issues = validate("#####\n#@..#\n#O.+#\n#####\n", StageFormat::Pusher, 1);
+        assert_eq!(
+            issues,
+            vec![Issue::Warning(Warning::BallOnDeadSquare {
+                line: 3,
+                col: 2
+            })]
+        );
+        assert!(validate("#####\n#@O+#\n#####\n", StageFormat::Pusher, 1).is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+//! Viewport for pusher
+//!
+//! A viewport is a window of the stage which fits in the terminal.
+//! It follows the player, and never goes out of the stage.
+
+use super::solver::Hint;
+use super::stage::{Direction, Stage};
+use super::theme::{Style, Theme};
+use super::vector2::Vector2;
+
+/// Describes a visible window of the stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    origin: Vector2,
+    width: usize,
+    height: usize,
+}
+
+impl Viewport {
+    /// Makes a viewport which shows the whole stage.
+    pub fn full(stage: &Stage) -> Viewport {
+        Viewport {
+            origin: Vector2::new(0, 0),
+            width: stage.width(),
+            height: stage.height(),
+        }
+    }
+    /// Makes a viewport of `width` x `height` centered on the player.\
+    /// It is clamped to the stage, and it is at least 1 x 1.
+    /// # Example
+    /// ```
+    /// use pusher::viewport::Viewport;
+    /// use pusher::{Stage, Vector2};
+    /// let stage = Stage::new("#@.......O+#").unwrap();
+    /// let view = Viewport::centered(&stage, 4, 3);
+    /// assert_eq!(view.origin(), Vector2::new(0, 0));
+    /// assert_eq!((view.width(), view.height()), (4, 1));
+    /// ```
+    pub fn centered(stage: &Stage, width: usize, height: usize) -> Viewport {
+        let width = width.max(1).min(stage.width());
+        let height = height.max(1).min(stage.height());
+        let player = stage.player_position();
+        Viewport {
+            origin: Vector2::new(
+                axis_origin(player.get_x(), width, stage.width()),
+                axis_origin(player.get_y(), height, stage.height()),
+            ),
+            width,
+            height,
+        }
+    }
+    /// Getter of the stage position at the top left corner
+    pub fn origin(&self) -> Vector2 {
+        self.origin
+    }
+    /// Getter of the width
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// Getter of the height
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Converts a position in the viewport to the stage position.
+    /// # Returns
+    /// Stage position, or `None` if the position is out of the viewport.
+    pub fn to_stage(&self, column: usize, row: usize) -> Option<Vector2> {
+        if column < self.width && row < self.height {
+            Some(self.origin + Vector2::new(column as i32, row as i32))
+        } else {
+            None
+        }
+    }
+    /// Renders visible tiles of `stage` row by row in `theme`.\
+    /// The ball of `hint` is drawn as an arrow to the direction of the push.
+    pub fn render(&self, stage: &Stage, theme: &Theme, hint: Option<&Hint>) -> String {
+        let mut string = String::new();
+        for row in 0..self.height {
+            for column in 0..self.width {
+                let pos = self.origin + Vector2::new(column as i32, row as i32);
+                match (stage.tile(pos), hint) {
+                    (Some(tile), Some(hint)) if hint.ball == pos => {
+                        let arrow = Style {
+                            glyph: format!("{:1$}", arrow(hint.direction), theme.width()),
+                            color: theme.style(tile).color.clone(),
+                        };
+                        string.push_str(&arrow.to_string());
+                    }
+                    (Some(tile), _) => string.push_str(&theme.style(tile).to_string()),
+                    (None, _) => (),
+                }
+            }
+            string.push_str("\r\n");
+        }
+        if theme.is_colored() {
+            string.push_str("\x1b[0m");
+        }
+        string
+    }
+}
+
+/// Returns an arrow character of the direction.
+fn arrow(direction: Direction) -> char {
+    match direction {
+        Direction::Up => '↑',
+        Direction::Down => '↓',
+        Direction::Left => '←',
+        Direction::Right => '→',
+    }
+}
+
+/// Returns the first visible index on an axis, which keeps `center` in the middle.
+fn axis_origin(center: i32, size: usize, total: usize) -> i32 {
+    (center - size as i32 / 2).max(0).min((total - size) as i32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn follows_player() {
+        let mut data = String::from("##########\n");
+        for y in 1..9 {
+            data.push_str(if y == 6 {
+                "#......@.#\n"
+            } else {
+                "#........#\n"
+            });
+        }
+        data.replace_range(12..14, "O+");
+        data.push_str("##########\n");
+        let stage = Stage::new(&data).unwrap();
+        let view = Viewport::centered(&stage, 4, 4);
+        assert_eq!(view.origin(), Vector2::new(5, 4));
+        assert_eq!(view.to_stage(2, 2), Some(stage.player_position()));
+        assert_eq!(view.to_stage(4, 0), None);
+        let view = Viewport::centered(&stage, 3, 20);
+        assert_eq!(view.origin(), Vector2::new(6, 0));
+        assert_eq!(view.height(), 10);
+        assert_eq!(
+            view.render(&stage, &Theme::plain(), None)
+                .matches("\r\n")
+                .count(),
+            10
+        );
+        assert_eq!(Viewport::centered(&stage, 0, 0).width(), 1);
+        assert_eq!(Viewport::full(&stage).width(), 10);
+    }
+}
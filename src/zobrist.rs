@@ -0,0 +1,150 @@
+//! Zobrist hashing for pusher
+//!
+//! A Zobrist key of a position is XOR of random numbers of the player square and every ball square.
+//! Moving a ball only XORs the numbers of the two squares, so `Stage` keeps the key of balls up to date.
+//! Random numbers are derived from square indices, so keys are the same in every run.
+
+use super::stage::{ObjectType, Tile};
+use std::collections::hash_map::{Entry, HashMap};
+
+/// Salt of random numbers, which is the fractional part of the golden ratio
+const SALT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Mixes bits of `x` (the finalizer of SplitMix64).
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(SALT);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns the random number of a ball on `index`.
+pub fn ball_key(index: usize) -> u64 {
+    mix(index as u64 * 2)
+}
+
+/// Returns the random number of the player on `index`.
+pub fn player_key(index: usize) -> u64 {
+    mix(index as u64 * 2 + 1)
+}
+
+/// Computes the key of every ball in `data` from scratch.
+pub(crate) fn balls_key(data: &[Tile]) -> u64 {
+    data.iter()
+        .enumerate()
+        .filter(|(_, tile)| tile.object() == Some(ObjectType::Ball))
+        .fold(0, |key, (index, _)| key ^ ball_key(index))
+}
+
+/// Describes a map from Zobrist keys to values, such as searched depth or best cost.\
+/// It keeps at most `capacity` entries, and new keys are ignored when it is full.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable<V> {
+    map: HashMap<u64, V>,
+    capacity: usize,
+}
+
+impl<V> TranspositionTable<V> {
+    /// Makes an empty table without a limit.
+    pub fn new() -> TranspositionTable<V> {
+        TranspositionTable::with_capacity(usize::MAX)
+    }
+    /// Makes an empty table which keeps at most `capacity` entries.
+    pub fn with_capacity(capacity: usize) -> TranspositionTable<V> {
+        TranspositionTable {
+            map: HashMap::new(),
+            capacity,
+        }
+    }
+    /// Returns the value of `key`.
+    pub fn get(&self, key: u64) -> Option<&V> {
+        self.map.get(&key)
+    }
+    /// Checks the table has `key`.
+    pub fn contains(&self, key: u64) -> bool {
+        self.map.contains_key(&key)
+    }
+    /// Stores `value` of `key`, replacing the old value.
+    /// # Returns
+    /// `false` if the table is full and `key` is new, so the value is not stored.
+    pub fn insert(&mut self, key: u64, value: V) -> bool {
+        let full = self.map.len() >= self.capacity;
+        match self.map.entry(key) {
+            Entry::Occupied(mut entry) => {
+                entry.insert(value);
+                true
+            }
+            Entry::Vacant(_) if full => false,
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                true
+            }
+        }
+    }
+    /// Stores `value` of `key` only if `better` says it is better than the old value.
+    /// # Returns
+    /// `true` if the value is stored.
+    /// # Example
+    /// ```
+    /// use pusher::zobrist::TranspositionTable;
+    /// let mut table = TranspositionTable::new();
+    /// assert!(table.insert_if(7, 10, |new, old| new < old));
+    /// assert!(!table.insert_if(7, 12, |new, old| new < old));
+    /// assert!(table.insert_if(7, 8, |new, old| new < old));
+    /// assert_eq!(table.get(7), Some(&8));
+    /// ```
+    pub fn insert_if(&mut self, key: u64, value: V, better: impl FnOnce(&V, &V) -> bool) -> bool {
+        match self.map.get(&key) {
+            Some(old) if !better(&value, old) => false,
+            _ => self.insert(key, value),
+        }
+    }
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    /// Checks the table has no entry.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
+impl<V> Default for TranspositionTable<V> {
+    fn default() -> TranspositionTable<V> {
+        TranspositionTable::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn capacity() {
+        let mut table = TranspositionTable::with_capacity(2);
+        assert!(table.insert(1, 'a'));
+        assert!(table.insert(2, 'b'));
+        assert!(!table.insert(3, 'c'));
+        assert!(table.insert(1, 'd'));
+        assert_eq!(
+            (table.len(), table.get(1), table.contains(3)),
+            (2, Some(&'d'), false)
+        );
+        table.clear();
+        assert!(table.is_empty());
+    }
+    #[test]
+    fn keys() {
+        assert_ne!(ball_key(0), player_key(0));
+        assert_ne!(ball_key(1), ball_key(2));
+        let data = [
+            Tile::Plain(ObjectType::Ball),
+            Tile::Wall,
+            Tile::Goal(ObjectType::Ball),
+        ];
+        assert_eq!(balls_key(&data), ball_key(0) ^ ball_key(2));
+    }
+}